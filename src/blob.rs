@@ -0,0 +1,351 @@
+/*!
+ * Chunked hashing for large blobs. Currently supports:
+ * - Splitting a file or stream into fixed-size chunks and building a Merkle tree over them
+ * - Proofs that a single chunk belongs to a blob with a given root
+ * - Verified random-access reads of a single chunk
+ * - Resumable, verified downloads
+ *
+ * Unlike `merkle::MerkleTree`, which keeps its leaves sorted, a `BlobTree` keeps its
+ * chunks in the order they appear in the blob. Parent hashes are computed with
+ * `PairHashing::Ordered`, and a node without a right sibling hashes its left child on its own.
+ *
+ * # Examples
+ *
+ * ```
+ * use std::io::Cursor;
+ * use newton::blob::BlobTree;
+ *
+ * let data = vec!(7u8; 10_000);
+ * let tree = BlobTree::from_reader(Cursor::new(&data), 1024).unwrap();
+ *
+ * let proof = tree.prove(3).unwrap();
+ * assert!(proof.verify(&tree.root(), &data[3 * 1024..4 * 1024]));
+ * ```
+ */
+
+use std::collections::BTreeSet;
+use std::io::{Read, Write, Seek, SeekFrom};
+
+use hash::{ChunkHash, MerkleRoot};
+use merkle::PairHashing;
+
+/// The chunk size used by `BlobTree::from_reader` callers who have no preference.
+pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+
+/**
+ * A Merkle tree over the fixed-size chunks of a blob.
+ *
+ * # Fields
+ * `chunk_size`: The size of every chunk but the last, in bytes.
+ *
+ * `len`: The total length of the blob, in bytes.
+ *
+ * `levels`: The hashes of every level of the tree. `levels[0]` holds the chunk hashes,
+ * and the last level holds only the root.
+ */
+pub struct BlobTree {
+    chunk_size: usize,
+    len: u64,
+    levels: Vec<Vec<String>>
+}
+
+/**
+ * A proof that a chunk is part of the blob with a given root.
+ *
+ * `siblings` contains the hash of the sibling at each level of the tree, starting at the
+ * chunks. A sibling is `None` when the node on the path has no right sibling.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkProof {
+    pub index: usize,
+    pub siblings: Vec<Option<String>>
+}
+
+/**
+ * A download of a blob which only accepts chunks that match a verified `BlobTree`.
+ *
+ * Chunks are written into `sink` at their offset as they arrive. If the download is interrupted,
+ * calling `BlobDownload::start` again with the same sink will keep every chunk that was already
+ * written correctly, so only the missing chunks need to be fetched again.
+ */
+pub struct BlobDownload<S: Read + Write + Seek> {
+    tree: BlobTree,
+    sink: S,
+    missing: BTreeSet<usize>
+}
+
+impl BlobTree {
+
+    /**
+     * Reads `reader` to the end, splitting it into chunks of `chunk_size` bytes, and builds
+     * a `BlobTree` over those chunks. An empty blob is treated as a single empty chunk.
+     *
+     * # Errors
+     * Will return an error if `chunk_size` is 0 or if reading fails.
+     */
+    pub fn from_reader<R: Read>(mut reader: R, chunk_size: usize) -> Result<Self, String> {
+
+        trace_span!("blob::from_reader", chunk_size);
+
+        if chunk_size == 0 {
+            return Err(String::from("Chunk size must be greater than zero"));
+        }
+
+        let mut chunk_hashes = Vec::new();
+        let mut len = 0;
+        let mut chunk = vec!(0; chunk_size);
+
+        loop {
+            let read = fill_chunk(&mut reader, &mut chunk)?;
+            if read == 0 && !chunk_hashes.is_empty() { break; }
+
+            chunk_hashes.push(ChunkHash::tagged_bytes(&chunk[..read]));
+            len += read as u64;
+
+            if read < chunk_size { break; }
+        }
+
+        trace_event!(chunks = chunk_hashes.len(), len, "hashed blob");
+        BlobTree::from_chunk_hashes(chunk_hashes, len, chunk_size)
+    }
+
+    /**
+     * Builds a `BlobTree` from chunk hashes that were computed elsewhere, for example a list
+     * of hashes received from a peer before starting a download. The caller should compare
+     * `root()` against a trusted root before relying on the result.
+     *
+     * # Errors
+     * Will return an error if `chunk_size` is 0, or if the number of hashes does not match
+     * the number of chunks a blob of `len` bytes would have.
+     */
+    pub fn from_chunk_hashes(chunk_hashes: Vec<ChunkHash>, len: u64, chunk_size: usize) -> Result<Self, String> {
+
+        if chunk_size == 0 {
+            return Err(String::from("Chunk size must be greater than zero"));
+        }
+
+        let expected = if len == 0 { 1 } else { ((len - 1) / chunk_size as u64 + 1) as usize };
+        if chunk_hashes.len() != expected {
+            return Err(format!("Expected {} chunk hashes but received {}", expected, chunk_hashes.len()));
+        }
+
+        let mut levels = vec!(chunk_hashes.into_iter().map(String::from).collect::<Vec<_>>());
+
+        while levels.len() == 1 || levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| {
+                    PairHashing::Ordered.combine(&pair[0], pair.get(1).map(|h| h.as_str())).into()
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Ok(BlobTree { chunk_size, len, levels })
+    }
+
+    /**
+     * Returns the root hash of the blob.
+     */
+    pub fn root(&self) -> MerkleRoot {
+        MerkleRoot::from_hex(self.levels[self.levels.len() - 1][0].clone())
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /**
+     * Returns the hash of the chunk at `index`, or `None` if there is no such chunk.
+     */
+    pub fn chunk_hash(&self, index: usize) -> Option<ChunkHash> {
+        self.levels[0].get(index).map(|hash| ChunkHash::from_hex(hash.clone()))
+    }
+
+    /**
+     * Returns the length of the chunk at `index`. Every chunk but the last has length `chunk_size`.
+     */
+    pub fn chunk_len(&self, index: usize) -> usize {
+        let start = index as u64 * self.chunk_size as u64;
+        if start >= self.len { return 0; }
+        ::std::cmp::min(self.chunk_size as u64, self.len - start) as usize
+    }
+
+    /**
+     * Generates a `ChunkProof` for the chunk at `index`, or `None` if there is no such chunk.
+     */
+    pub fn prove(&self, index: usize) -> Option<ChunkProof> {
+
+        if index >= self.chunk_count() { return None; }
+
+        let mut siblings = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            siblings.push(level.get(position ^ 1).cloned());
+            position /= 2;
+        }
+
+        Some(ChunkProof { index, siblings })
+    }
+
+    /**
+     * Reads the chunk at `index` from `source`, which should contain the blob starting at offset 0,
+     * and verifies it against this tree before returning it.
+     *
+     * # Errors
+     * Will return an error if there is no chunk at `index`, if reading fails, or if the chunk
+     * read from `source` does not match its hash.
+     */
+    pub fn read_chunk<R: Read + Seek>(&self, source: &mut R, index: usize) -> Result<Vec<u8>, String> {
+
+        if index >= self.chunk_count() {
+            return Err(format!("Chunk {} is out of bounds", index));
+        }
+
+        let mut chunk = vec!(0; self.chunk_len(index));
+        seek_to_chunk(source, index, self.chunk_size)?;
+        let read = fill_chunk(source, &mut chunk)?;
+
+        if read != chunk.len() {
+            return Err(format!("Chunk {} is truncated", index));
+        }
+        if ChunkHash::tagged_bytes(&chunk).as_str() != self.levels[0][index] {
+            return Err(format!("Chunk {} does not match its hash", index));
+        }
+
+        Ok(chunk)
+    }
+}
+
+impl ChunkProof {
+
+    /**
+     * Checks that `chunk` is the chunk at `self.index` of the blob with root `root`.
+     */
+    pub fn verify(&self, root: &MerkleRoot, chunk: &[u8]) -> bool {
+
+        let mut hash = String::from(ChunkHash::tagged_bytes(chunk));
+        let mut position = self.index;
+
+        for sibling in &self.siblings {
+            hash = match (position % 2, sibling) {
+                (0, sibling) => PairHashing::Ordered.combine(&hash, sibling.as_ref().map(|s| s.as_str())),
+                (_, Some(sibling)) => PairHashing::Ordered.combine(sibling, Some(&hash)),
+                (_, None) => { return false; }
+            }.into();
+            position /= 2;
+        }
+
+        position == 0 && hash == root.as_str()
+    }
+}
+
+impl<S: Read + Write + Seek> BlobDownload<S> {
+
+    /**
+     * Starts or resumes a download of the blob described by `tree` into `sink`.
+     *
+     * Every chunk already present in `sink` which matches its hash is kept, so calling `start`
+     * on a partially written sink resumes the download.
+     *
+     * # Errors
+     * Will return an error if `tree` does not have the root `root`.
+     */
+    pub fn start(root: &MerkleRoot, tree: BlobTree, mut sink: S) -> Result<Self, String> {
+
+        if tree.root() != *root {
+            return Err(String::from("Blob tree does not match the expected root"));
+        }
+
+        let mut missing = BTreeSet::new();
+        for index in 0..tree.chunk_count() {
+            if tree.read_chunk(&mut sink, index).is_err() {
+                missing.insert(index);
+            }
+        }
+
+        Ok(BlobDownload { tree, sink, missing })
+    }
+
+    /**
+     * Returns the indices of the chunks which still have to be downloaded.
+     */
+    pub fn missing(&self) -> Vec<usize> {
+        self.missing.iter().cloned().collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /**
+     * Verifies `chunk` against the hash of the chunk at `index` and writes it into the sink.
+     *
+     * # Errors
+     * Will return an error, without writing anything, if there is no chunk at `index` or if
+     * `chunk` does not match its hash. Will also return an error if writing fails.
+     */
+    pub fn write_chunk(&mut self, index: usize, chunk: &[u8]) -> Result<(), String> {
+
+        match self.tree.chunk_hash(index) {
+            Some(ref hash) if *hash == ChunkHash::tagged_bytes(chunk) => {}
+            Some(_) => { return Err(format!("Chunk {} does not match its hash", index)); }
+            None => { return Err(format!("Chunk {} is out of bounds", index)); }
+        }
+
+        seek_to_chunk(&mut self.sink, index, self.tree.chunk_size)?;
+        self.sink.write_all(chunk).map_err(|e| e.to_string())?;
+        self.missing.remove(&index);
+
+        Ok(())
+    }
+
+    /**
+     * Finishes the download and returns the sink.
+     *
+     * # Errors
+     * Will return an error if some chunks are still missing.
+     */
+    pub fn finish(mut self) -> Result<S, String> {
+        if !self.is_complete() {
+            return Err(format!("{} chunks are still missing", self.missing.len()));
+        }
+        self.sink.flush().map_err(|e| e.to_string())?;
+        Ok(self.sink)
+    }
+}
+
+/*
+ * Fills as much of `chunk` as possible from `reader`, returning the number of bytes read.
+ * Fewer bytes than `chunk.len()` are only returned at the end of the stream.
+ */
+pub(crate) fn fill_chunk<R: Read>(reader: &mut R, chunk: &mut [u8]) -> Result<usize, String> {
+    let mut read = 0;
+    while read < chunk.len() {
+        match reader.read(&mut chunk[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(ref e) if e.kind() == ::std::io::ErrorKind::Interrupted => {}
+            Err(e) => { return Err(e.to_string()); }
+        }
+    }
+    Ok(read)
+}
+
+fn seek_to_chunk<S: Seek>(stream: &mut S, index: usize, chunk_size: usize) -> Result<(), String> {
+    stream.seek(SeekFrom::Start(index as u64 * chunk_size as u64))
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
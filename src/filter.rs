@@ -0,0 +1,236 @@
+/*!
+ * Probabilistic set membership filters. Currently supports:
+ * - The `ProbabilisticSet` trait shared by every filter
+ * - A cuckoo filter which, unlike a Bloom filter, can remove items without being rebuilt
+ *
+ * A probabilistic set never reports that an inserted item is missing, but may occasionally
+ * report that an item is present when it was never inserted.
+ *
+ * # Examples
+ *
+ * ```
+ * use newton::filter::{CuckooFilter, ProbabilisticSet};
+ *
+ * let mut filter = CuckooFilter::new(1000);
+ * let tx = String::from("some transaction");
+ *
+ * filter.insert(&tx).unwrap();
+ * assert!(filter.contains(&tx));
+ *
+ * assert!(filter.remove(&tx));
+ * assert!(!filter.contains(&tx));
+ * ```
+ */
+
+use crypto::sha2::Sha256;
+use crypto::digest::Digest;
+
+use hash::Hashable;
+
+/// The number of fingerprints held by each bucket of a `CuckooFilter`.
+pub const BUCKET_SIZE: usize = 4;
+
+/// The number of times `CuckooFilter::insert` relocates a fingerprint before giving up.
+pub const MAX_KICKS: usize = 500;
+
+/**
+ * A set which answers membership queries with a small chance of false positives.
+ */
+pub trait ProbabilisticSet<T: Hashable> {
+
+    /**
+     * Adds `item` to the set.
+     *
+     * # Errors
+     * Will return an error if the set is too full to hold `item`.
+     */
+    fn insert(&mut self, item: &T) -> Result<(), String>;
+
+    /**
+     * Returns `true` if `item` may be in the set, and `false` if it definitely is not.
+     */
+    fn contains(&self, item: &T) -> bool;
+}
+
+/**
+ * A cuckoo filter, which stores a 16 bit fingerprint of each item in one of two buckets.
+ *
+ * The false positive rate is roughly `2 * BUCKET_SIZE / 2^16`. The filter can hold about 95% of
+ * its capacity before inserts start to fail.
+ *
+ * # Fields
+ * `buckets`: The buckets of fingerprints. A fingerprint of 0 marks an empty slot. The number of
+ * buckets is a power of two, so an item's alternate bucket can be computed from its fingerprint.
+ *
+ * `victim`: A fingerprint and its bucket which could not be placed after `MAX_KICKS` relocations.
+ * While it is set the filter is full, but no inserted item has been lost.
+ *
+ * `kicks`: Counts relocations, used to pick which fingerprint to evict next.
+ */
+#[derive(Clone, Debug)]
+pub struct CuckooFilter {
+    buckets: Vec<[u16; BUCKET_SIZE]>,
+    victim: Option<(usize, u16)>,
+    len: usize,
+    kicks: usize
+}
+
+impl CuckooFilter {
+
+    /**
+     * Creates an empty filter with room for at least `capacity` items.
+     */
+    pub fn new(capacity: usize) -> Self {
+        let bucket_count = ::std::cmp::max(capacity.div_ceil(BUCKET_SIZE), 1).next_power_of_two();
+        CuckooFilter { buckets: vec!([0; BUCKET_SIZE]; bucket_count), victim: None, len: 0, kicks: 0 }
+    }
+
+    /**
+     * Returns the number of items in the filter.
+     */
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /**
+     * Returns the number of fingerprints the filter has room for.
+     */
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_SIZE
+    }
+
+    /**
+     * Removes one copy of `item` from the filter. Only items which were inserted should be
+     * removed, otherwise an item sharing their fingerprint may be removed in their place.
+     *
+     * # Return Value
+     * Returns `true` if a fingerprint of `item` was found and removed.
+     */
+    pub fn remove<T: Hashable>(&mut self, item: &T) -> bool {
+
+        let (fingerprint, first) = self.locate(item);
+        let second = self.alternate(first, fingerprint);
+
+        if self.victim == Some((first, fingerprint)) || self.victim == Some((second, fingerprint)) {
+            self.victim = None;
+            self.len -= 1;
+            return true;
+        }
+
+        for &index in &[first, second] {
+            if let Some(slot) = self.buckets[index].iter_mut().find(|slot| **slot == fingerprint) {
+                *slot = 0;
+                self.len -= 1;
+                self.place_victim();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /*
+     * Returns the fingerprint of `item`, which is never 0, and its first bucket.
+     */
+    fn locate<T: Hashable>(&self, item: &T) -> (u16, usize) {
+        let digest = digest(&item.get_hash());
+        let fingerprint = match u16::from(digest[0]) << 8 | u16::from(digest[1]) {
+            0 => 1,
+            fingerprint => fingerprint
+        };
+        let index = digest[2..10].iter().fold(0, |acc, &byte| (acc << 8) | byte as usize);
+        (fingerprint, index & (self.buckets.len() - 1))
+    }
+
+    /*
+     * Returns the other bucket a fingerprint stored in bucket `index` may live in. Applying this
+     * twice returns `index`.
+     */
+    fn alternate(&self, index: usize, fingerprint: u16) -> usize {
+        let digest = digest(&fingerprint.to_string());
+        let offset = digest[..8].iter().fold(0, |acc, &byte| (acc << 8) | byte as usize);
+        (index ^ offset) & (self.buckets.len() - 1)
+    }
+
+    fn try_place(&mut self, index: usize, fingerprint: u16) -> bool {
+        match self.buckets[index].iter_mut().find(|slot| **slot == 0) {
+            Some(slot) => { *slot = fingerprint; true }
+            None => false
+        }
+    }
+
+    /*
+     * Places `fingerprint` in bucket `index` or its alternate, relocating other fingerprints
+     * to make room if needed. Leaves the last displaced fingerprint in `victim` on failure.
+     */
+    fn place(&mut self, mut index: usize, mut fingerprint: u16) -> bool {
+
+        let alternate = self.alternate(index, fingerprint);
+        if self.try_place(index, fingerprint) || self.try_place(alternate, fingerprint) {
+            return true;
+        }
+
+        for _ in 0..MAX_KICKS {
+            self.kicks = self.kicks.wrapping_add(1);
+            let slot = self.kicks % BUCKET_SIZE;
+
+            ::std::mem::swap(&mut fingerprint, &mut self.buckets[index][slot]);
+            index = self.alternate(index, fingerprint);
+
+            if self.try_place(index, fingerprint) {
+                return true;
+            }
+        }
+
+        self.victim = Some((index, fingerprint));
+        false
+    }
+
+    /*
+     * Tries to move the victim back into the table once a slot has been freed.
+     */
+    fn place_victim(&mut self) {
+        if let Some((index, fingerprint)) = self.victim.take() {
+            self.place(index, fingerprint);
+        }
+    }
+}
+
+impl<T: Hashable> ProbabilisticSet<T> for CuckooFilter {
+
+    fn insert(&mut self, item: &T) -> Result<(), String> {
+
+        if self.victim.is_some() {
+            return Err(String::from("Cuckoo filter is full"));
+        }
+
+        let (fingerprint, index) = self.locate(item);
+        self.len += 1;
+        self.place(index, fingerprint);
+        Ok(())
+    }
+
+    fn contains(&self, item: &T) -> bool {
+
+        let (fingerprint, first) = self.locate(item);
+        let second = self.alternate(first, fingerprint);
+
+        self.buckets[first].contains(&fingerprint) ||
+        self.buckets[second].contains(&fingerprint) ||
+        self.victim == Some((first, fingerprint)) ||
+        self.victim == Some((second, fingerprint))
+    }
+}
+
+fn digest(data: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.input_str("newton/cuckoo:");
+    hasher.input_str(data);
+    let mut digest = [0; 32];
+    hasher.result(&mut digest);
+    digest
+}
@@ -1,26 +1,235 @@
-#[allow(dead_code)]
+use std::fmt;
 
 use crypto::sha2::{Sha256};
-use crypto::digest::Digest;
+
+use merkle::PairHashing;
 
 pub struct Block<T> {
     previous: Option<HashPointer<Block<T>>>,
-    header_hash: u128,
+    header_hash: BlockHash,
     content: Vec<T>
 }
 
+pub use crypto::digest::Digest;
+
+/**
+ * An item which can be hashed, for example to be stored in the leaf of a `MerkleTree`.
+ *
+ * Implementors must provide `get_hash`. Large structs and byte streams can also implement
+ * `hash_into` to feed their contents to a hasher piece by piece instead of first building them
+ * into one `String`, and then implement `get_hash` with `stream_hash`. A `MerkleTree` hashes its
+ * leaves through `hash_into`, so such items are never built into a `String` by the tree.
+ */
 pub trait Hashable {
+
+    /**
+     * Returns the hex encoded hash of this item.
+     */
     fn get_hash(&self) -> String;
+
+    /**
+     * Feeds this item's contents into `hasher`. By default, this feeds in the result of `get_hash`.
+     */
+    fn hash_into(&self, hasher: &mut dyn Digest) {
+        hasher.input_str(&self.get_hash());
+    }
+
+    /**
+     * Returns the hash a `MerkleTree` built with `pair_hashing` stores in the leaf holding this item. By default,
+     * this is `pair_hashing.leaf(self)`, which is `LeafHash::of(self)` for every mode but `OpenZeppelin`.
+     */
+    fn leaf_hash(&self, pair_hashing: PairHashing) -> LeafHash {
+        pair_hashing.leaf(self)
+    }
+}
+
+/**
+ * Returns the sha2 digest of everything `item.hash_into` feeds to the hasher. Types which
+ * implement `hash_into` can use this as their `get_hash`.
+ *
+ * *Note*: If `item` does not override `hash_into`, this hashes the result of its `get_hash`, so
+ * calling this from `get_hash` without overriding `hash_into` will recurse forever.
+ */
+pub fn stream_hash<H: Hashable + ?Sized>(item: &H) -> String {
+    let mut hasher = Sha256::new();
+    item.hash_into(&mut hasher);
+    hasher.result_str()
 }
 
 impl Hashable for String {
     fn get_hash(&self) -> String {
-        let mut hasher = Sha256::new();
+        stream_hash(self)
+    }
+
+    fn hash_into(&self, hasher: &mut dyn Digest) {
         hasher.input_str(self);
-        hasher.result_str()
     }
 }
 
+impl Hashable for Vec<u8> {
+    fn get_hash(&self) -> String {
+        stream_hash(self)
+    }
+
+    fn hash_into(&self, hasher: &mut dyn Digest) {
+        hasher.input(self);
+    }
+}
+
+/**
+ * Computes sha2(tag || ":" || data). Prefixing the data with a domain tag guarantees that
+ * two hashes computed for different purposes can never collide, even when the underlying
+ * data is identical.
+ */
+pub fn tagged_hash(tag: &str, data: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input_str(tag);
+    hasher.input_str(":");
+    hasher.input_str(data);
+    hasher.result_str()
+}
+
+/**
+ * Computes sha2(tag || ":" || data) for raw bytes. See `tagged_hash`.
+ */
+pub fn tagged_hash_bytes(tag: &str, data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input_str(tag);
+    hasher.input_str(":");
+    hasher.input(data);
+    hasher.result_str()
+}
+
+/*
+ * Declares a newtype around a hex encoded sha2 digest. Values of different newtypes cannot be
+ * compared with one another. Newtypes declared with a tag also get their own domain tag.
+ */
+macro_rules! hash_newtype {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(String);
+
+        impl $name {
+
+            /**
+             * Wraps an already computed hex digest. No hashing takes place, so the caller
+             * is responsible for making sure `hex` really belongs to this domain.
+             */
+            pub fn from_hex(hex: String) -> Self {
+                $name(hex)
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            pub fn into_string(self) -> String {
+                self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(hash: $name) -> String {
+                hash.0
+            }
+        }
+    };
+    ($(#[$attr:meta])* $name:ident, $tag:expr) => {
+        hash_newtype!($(#[$attr])* $name);
+
+        impl $name {
+
+            /// The domain tag prepended to data hashed with `tagged`.
+            pub const TAG: &'static str = $tag;
+
+            /**
+             * Hashes `data` under this type's domain tag.
+             */
+            pub fn tagged(data: &str) -> Self {
+                $name(tagged_hash(Self::TAG, data))
+            }
+
+            /**
+             * Hashes the raw bytes `data` under this type's domain tag.
+             */
+            pub fn tagged_bytes(data: &[u8]) -> Self {
+                $name(tagged_hash_bytes(Self::TAG, data))
+            }
+        }
+    }
+}
+
+hash_newtype!(
+    /// The hash of a block header.
+    BlockHash, "newton/block"
+);
+hash_newtype!(
+    /// The identifier of a transaction.
+    TxId, "newton/tx"
+);
+hash_newtype!(
+    /// The `mrkl_root` of a node in a `MerkleTree`.
+    MerkleRoot, "newton/merkle-node"
+);
+hash_newtype!(
+    /// The hash of a single item stored in the leaf of a `MerkleTree`. Leaves have their own domain
+    /// tag, so a leaf can never hash the same as a node.
+    LeafHash, "newton/merkle-leaf"
+);
+hash_newtype!(
+    /// The hash of a single chunk of a blob, the leaf of a `BlobTree`.
+    ChunkHash, "newton/blob-chunk"
+);
+hash_newtype!(
+    /// The hash of a node in a `MerkleSumTree`, committing to the node's sum as well as its children.
+    SumHash, "newton/sum-node"
+);
+
+impl LeafHash {
+
+    /**
+     * Returns the hash a `MerkleTree` stores in the leaf holding `item`, sha2("newton/merkle-leaf:" || stream),
+     * where stream is everything `item.hash_into` feeds to the hasher. The item is streamed straight into the
+     * hasher. Unless it overrides `hash_into`, this is the same as `LeafHash::tagged(&item.get_hash())`.
+     */
+    pub fn of<T: Hashable + ?Sized>(item: &T) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.input_str(Self::TAG);
+        hasher.input_str(":");
+        item.hash_into(&mut hasher);
+        LeafHash(hasher.result_str())
+    }
+}
+
+/**
+ * A `LeafHash` is already the leaf hash of some item, so a `MerkleTree<LeafHash>` stores it in its leaf as is
+ * instead of hashing it again, under every `PairHashing` mode. Such a tree therefore has the same leaf hashes as a tree built from the items
+ * themselves. See `MerkleTree::from_leaf_hashes`. Its `get_hash` hashes the hex digest like any other string.
+ */
+impl Hashable for LeafHash {
+    fn get_hash(&self) -> String {
+        self.0.get_hash()
+    }
+
+    fn leaf_hash(&self, _pair_hashing: PairHashing) -> LeafHash {
+        self.clone()
+    }
+}
+
+#[derive(Clone)]
 pub struct HashPointer<T> {
     pub hash: String,
     pub ptr: Box<T>
@@ -32,12 +241,30 @@ impl<T> HashPointer<T> where T: Hashable {
         HashPointer { hash: item.get_hash(), ptr: Box::new(item) }
     }
 
+    /**
+     * Points to `item` from the leaf of a `MerkleTree` built with `pair_hashing`, storing the item's `leaf_hash`
+     * instead of its `get_hash`.
+     */
+    pub fn to_leaf(item: T, pair_hashing: PairHashing) -> Self {
+        HashPointer { hash: item.leaf_hash(pair_hashing).into_string(), ptr: Box::new(item) }
+    }
+
     pub fn verify_hash(&self) -> bool {
-        if self.ptr.get_hash() == self.hash {
-            true
-        } else {
-            false
-        }
+        self.ptr.get_hash() == self.hash
     }
-}
 
+    /**
+     * Checks the hash of a pointer created with `to_leaf`.
+     */
+    pub fn verify_leaf_hash(&self, pair_hashing: PairHashing) -> bool {
+        self.ptr.leaf_hash(pair_hashing).as_str() == self.hash
+    }
+
+    /**
+     * Returns the stored hash of a pointer created with `to_leaf`. This is the same as
+     * `self.ptr.leaf_hash(pair_hashing)` as long as `verify_leaf_hash` holds.
+     */
+    pub fn leaf_hash(&self) -> LeafHash {
+        LeafHash::from_hex(self.hash.clone())
+    }
+}
@@ -0,0 +1,288 @@
+/*!
+ * Invertible Bloom lookup tables and set reconciliation. Currently supports:
+ * - Inserting and removing keys
+ * - Subtracting one table from another
+ * - Listing the keys left in a table by peeling pure cells
+ * - Reconciling two sets with communication proportional to their difference
+ *
+ * To reconcile two sets which are mostly the same, both peers build an `Iblt` of their own set
+ * with the same number of cells and hash functions, and one peer sends its table to the other.
+ * The receiving peer calls `Iblt::reconcile`, which subtracts the tables so every key the sets
+ * share cancels out, and decodes what is left. The table only has to be large enough for the
+ * difference, not for the sets themselves. If decoding fails, the table was too small, and the
+ * peers should try again with more cells.
+ *
+ * # Examples
+ *
+ * ```
+ * use newton::iblt::Iblt;
+ *
+ * let ours: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+ * let theirs: Vec<String> = (1..101).map(|i| i.to_string()).collect();
+ *
+ * let local = Iblt::from_items(&ours, 30, 3).unwrap();
+ * let remote = Iblt::from_items(&theirs, 30, 3).unwrap();
+ *
+ * let difference = Iblt::reconcile(&local, &remote).unwrap();
+ * assert_eq!(difference.local_only, vec!(Iblt::key(&String::from("0"))));
+ * assert_eq!(difference.remote_only, vec!(Iblt::key(&String::from("100"))));
+ * ```
+ */
+
+use crypto::sha2::Sha256;
+use crypto::digest::Digest;
+
+use hash::Hashable;
+
+/// The key of an item in an `Iblt`, derived from the item's hash with `Iblt::key`.
+pub type Key = [u8; 32];
+
+/// The number of hash functions used by `Iblt::with_capacity`.
+pub const DEFAULT_HASH_COUNT: usize = 3;
+
+/**
+ * An invertible Bloom lookup table.
+ *
+ * The cells are split into `hash_count` equal subtables, and each key is added to one cell of
+ * each subtable, so a key never lands in the same cell twice.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Iblt {
+    hash_count: usize,
+    cells: Vec<Cell>
+}
+
+/**
+ * The difference between two sets, as computed by `Iblt::reconcile`.
+ *
+ * `local_only`: The keys of the items only in the local set, which the remote peer is missing.
+ *
+ * `remote_only`: The keys of the items only in the remote set, which the local peer is missing.
+ *
+ * Both lists are sorted.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SetDifference {
+    pub local_only: Vec<Key>,
+    pub remote_only: Vec<Key>
+}
+
+/*
+ * A single cell of an `Iblt`. `key_sum` is the XOR of every key added to the cell, and
+ * `check_sum` is the XOR of a checksum of each of those keys, which tells us whether a cell
+ * with a count of 1 or -1 really holds a single key.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Cell {
+    count: i64,
+    key_sum: Key,
+    check_sum: u64
+}
+
+impl Iblt {
+
+    /**
+     * Creates an empty table with at least `cell_count` cells and `hash_count` hash functions.
+     * The number of cells is rounded up to a multiple of `hash_count`.
+     *
+     * # Errors
+     * Will return an error if `cell_count` or `hash_count` is 0.
+     */
+    pub fn new(cell_count: usize, hash_count: usize) -> Result<Self, String> {
+
+        if cell_count == 0 || hash_count == 0 {
+            return Err(String::from("An IBLT needs at least one cell and one hash function"));
+        }
+
+        let subtable_len = (cell_count - 1) / hash_count + 1;
+        let empty = Cell { count: 0, key_sum: [0; 32], check_sum: 0 };
+
+        Ok(Iblt { hash_count, cells: vec!(empty; subtable_len * hash_count) })
+    }
+
+    /**
+     * Creates an empty table which can usually decode up to `difference` keys, using
+     * `DEFAULT_HASH_COUNT` hash functions.
+     */
+    pub fn with_capacity(difference: usize) -> Self {
+        let cell_count = ::std::cmp::max(difference + difference / 2, 1) + DEFAULT_HASH_COUNT;
+        Iblt::new(cell_count, DEFAULT_HASH_COUNT).unwrap()
+    }
+
+    /**
+     * Creates a table with `cell_count` cells and `hash_count` hash functions holding the key of
+     * every item in `items`. See `Iblt::new`.
+     */
+    pub fn from_items<T: Hashable>(items: &[T], cell_count: usize, hash_count: usize) -> Result<Self, String> {
+        let mut table = Iblt::new(cell_count, hash_count)?;
+        for item in items {
+            table.insert(&Iblt::key(item));
+        }
+        Ok(table)
+    }
+
+    /**
+     * Returns the key of `item`, which is the sha2 digest of its hash.
+     */
+    pub fn key<T: Hashable>(item: &T) -> Key {
+        let mut hasher = Sha256::new();
+        hasher.input_str(&item.get_hash());
+        let mut key = [0; 32];
+        hasher.result(&mut key);
+        key
+    }
+
+    pub fn insert(&mut self, key: &Key) {
+        self.update(key, 1);
+    }
+
+    /**
+     * Removes `key` from the table. Removing a key which was never inserted leaves a cell with a
+     * negative count, which is how `subtract` records keys only found in the other table.
+     */
+    pub fn remove(&mut self, key: &Key) {
+        self.update(key, -1);
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn hash_count(&self) -> usize {
+        self.hash_count
+    }
+
+    /**
+     * Subtracts `other` from this table. Keys found in both tables cancel out, keys only in this
+     * table are left with a positive count, and keys only in `other` with a negative count.
+     *
+     * # Errors
+     * Will return an error if the tables have different numbers of cells or hash functions.
+     */
+    pub fn subtract(&self, other: &Iblt) -> Result<Iblt, String> {
+
+        if self.cells.len() != other.cells.len() || self.hash_count != other.hash_count {
+            return Err(String::from("Only tables with the same dimensions can be subtracted"));
+        }
+
+        let cells = self.cells.iter().zip(&other.cells).map(|(a, b)| {
+            let mut cell = *a;
+            cell.count -= b.count;
+            xor_into(&mut cell.key_sum, &b.key_sum);
+            cell.check_sum ^= b.check_sum;
+            cell
+        }).collect();
+
+        Ok(Iblt { hash_count: self.hash_count, cells })
+    }
+
+    /**
+     * Lists every key in the table by repeatedly removing keys from cells which hold exactly one.
+     *
+     * # Return Value
+     * Returns the keys with a positive count in `local_only`, and the keys with a negative count
+     * in `remote_only`.
+     *
+     * # Errors
+     * Will return an error if the table holds too many keys to be decoded.
+     */
+    pub fn decode(mut self) -> Result<SetDifference, String> {
+
+        let mut difference = SetDifference::default();
+        let mut pure: Vec<usize> = (0..self.cells.len()).filter(|&i| self.is_pure(i)).collect();
+
+        while let Some(index) = pure.pop() {
+
+            if !self.is_pure(index) { continue; }
+
+            let cell = self.cells[index];
+            if cell.count == 1 {
+                difference.local_only.push(cell.key_sum);
+            } else {
+                difference.remote_only.push(cell.key_sum);
+            }
+
+            for position in self.positions(&cell.key_sum) {
+                self.remove_cell(position, &cell.key_sum, cell.count);
+                if self.is_pure(position) {
+                    pure.push(position);
+                }
+            }
+        }
+
+        if self.cells.iter().any(|cell| cell.count != 0 || cell.key_sum != [0; 32] || cell.check_sum != 0) {
+            return Err(String::from("The table holds too many keys to decode"));
+        }
+
+        difference.local_only.sort();
+        difference.remote_only.sort();
+        Ok(difference)
+    }
+
+    /**
+     * Computes the difference between the local set, summarized by `local`, and a remote set,
+     * summarized by `remote`. Both tables must have been created with the same dimensions.
+     *
+     * # Errors
+     * Will return an error if the tables have different dimensions, or if the sets differ by
+     * too many items for the tables to decode. In the latter case the peers should retry with
+     * larger tables.
+     */
+    pub fn reconcile(local: &Iblt, remote: &Iblt) -> Result<SetDifference, String> {
+        local.subtract(remote)?.decode()
+    }
+
+    fn update(&mut self, key: &Key, count: i64) {
+        let check = checksum(key);
+        for position in self.positions(key) {
+            let cell = &mut self.cells[position];
+            cell.count += count;
+            xor_into(&mut cell.key_sum, key);
+            cell.check_sum ^= check;
+        }
+    }
+
+    fn remove_cell(&mut self, position: usize, key: &Key, count: i64) {
+        let cell = &mut self.cells[position];
+        cell.count -= count;
+        xor_into(&mut cell.key_sum, key);
+        cell.check_sum ^= checksum(key);
+    }
+
+    fn is_pure(&self, index: usize) -> bool {
+        let cell = &self.cells[index];
+        (cell.count == 1 || cell.count == -1) && cell.check_sum == checksum(&cell.key_sum)
+    }
+
+    /*
+     * Returns the cell `key` occupies in each subtable.
+     */
+    fn positions(&self, key: &Key) -> Vec<usize> {
+        let subtable_len = self.cells.len() / self.hash_count;
+        (0..self.hash_count).map(|i| {
+            let mut hasher = Sha256::new();
+            hasher.input_str(&format!("newton/iblt-cell:{}:", i));
+            hasher.input(key);
+            i * subtable_len + (digest_u64(&mut hasher) % subtable_len as u64) as usize
+        }).collect()
+    }
+}
+
+fn checksum(key: &Key) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.input_str("newton/iblt-check:");
+    hasher.input(key);
+    digest_u64(&mut hasher)
+}
+
+fn digest_u64(hasher: &mut Sha256) -> u64 {
+    let mut digest = [0; 32];
+    hasher.result(&mut digest);
+    digest[..8].iter().fold(0, |acc, &byte| (acc << 8) | byte as u64)
+}
+
+fn xor_into(target: &mut Key, key: &Key) {
+    for (t, k) in target.iter_mut().zip(key.iter()) {
+        *t ^= k;
+    }
+}
@@ -0,0 +1,256 @@
+/*!
+ * Proof of liabilities on top of `MerkleSumTree`. Currently supports:
+ * - Splitting each user's balance into randomized shares under salted leaf ids
+ * - Blinding every sum in the tree except the total with random offsets
+ * - Exporting a per-user `LiabilityProof` covering every share of their balance
+ * - Verifying a `LiabilityProof` against a published root hash and total
+ *
+ * A custodian publishes the root hash and total of a `Liabilities` tree, and hands each user
+ * their `LiabilityProof`. Each user checks that their whole balance is counted towards the
+ * published total. The more users who check, the harder it is to leave anyone out.
+ *
+ * Each balance is split into several shares with random amounts, and the shares of every user
+ * are shuffled together, so the sibling of a share is usually a random fragment of some other
+ * balance rather than the balance itself. Leaf ids are derived from a per-user salt, so they
+ * reveal nothing about who a share belongs to, but each user can check that their shares are
+ * bound to their own id.
+ *
+ * Every share is also given a random blinding offset, and the offsets of all shares add up to 0
+ * modulo 2^64. The tree is built with `SumMode::Blinded` over each share's amount plus its offset,
+ * so the root still carries the true total, but every sibling in a proof carries its subtree's
+ * total plus the offsets below it. Without the offsets, which each user only learns for their own
+ * shares, those sums look random and reveal nothing about other balances.
+ *
+ * *Limitation*: Since blinded sums wrap around, a verifier cannot tell a share with a huge amount
+ * from one with a negative amount, so a custodian could hide a negative share in a subtree no user
+ * checks. Ruling that out would need commitments with range proofs, which this module does not
+ * implement.
+ *
+ * The randomness comes from a `seed` supplied by the caller, which should be secret and fresh
+ * for every publication. The same seed and balances always produce the same tree.
+ *
+ * # Examples
+ *
+ * ```
+ * use newton::liabilities::Liabilities;
+ *
+ * let balances = vec!(
+ *     (String::from("alice"), 50),
+ *     (String::from("bob"), 20),
+ *     (String::from("carol"), 30)
+ * );
+ * let liabilities = Liabilities::construct(balances, 4, "a secret seed").unwrap();
+ * let (root, total) = (liabilities.root().hash.clone(), liabilities.total());
+ *
+ * let proof = liabilities.proof("bob").unwrap();
+ * assert_eq!(proof.amount, 20);
+ * assert!(proof.verify(&root, total));
+ * ```
+ */
+
+use std::collections::HashMap;
+
+use hash::{tagged_hash, SumHash};
+use sum::{MerkleSumTree, SumMode, SumNode, SumProof};
+
+/**
+ * A `MerkleSumTree` over the randomized shares of every user's balance.
+ *
+ * # Fields
+ * `shares`: For each user, their salt and the index of each of their shares in the tree's leaves.
+ *
+ * `leaves`: The id, amount and blinding offset of every leaf of `tree`, in order.
+ */
+#[derive(Clone, Debug)]
+pub struct Liabilities {
+    tree: MerkleSumTree,
+    leaves: Vec<(String, u64, u64)>,
+    shares: HashMap<String, (String, Vec<usize>)>
+}
+
+/**
+ * A proof that every share of a user's balance is counted towards a published total.
+ *
+ * # Fields
+ * `user`: The id of the user, as passed to `Liabilities::construct`.
+ *
+ * `amount`: The user's balance, which should match what the user expects to be owed.
+ *
+ * `salt`: The user's salt, from which the ids of their shares are derived.
+ *
+ * `shares`: One `ShareProof` for each share of the balance, in the order the ids are derived.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LiabilityProof {
+    pub user: String,
+    pub amount: u64,
+    pub salt: String,
+    pub shares: Vec<ShareProof>
+}
+
+/**
+ * A proof that one share of a user's balance is a leaf of the `Liabilities` tree. The leaf carries
+ * `amount` plus `blinding`, modulo 2^64.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShareProof {
+    pub amount: u64,
+    pub blinding: u64,
+    pub proof: SumProof
+}
+
+impl Liabilities {
+
+    /**
+     * Splits every balance in `balances`, each a `(user, amount)` pair, into `parts` shares, and
+     * builds a blinded `MerkleSumTree` over the shares of every user in a shuffled order. The split,
+     * the shuffle, the salts and the blinding offsets are all derived from `seed`.
+     *
+     * # Errors
+     * Will return an error if `balances` is empty, if a user appears more than once, if `parts`
+     * is 0, or if the total of every balance overflows a `u64`.
+     */
+    pub fn construct(balances: Vec<(String, u64)>, parts: usize, seed: &str) -> Result<Self, String> {
+
+        trace_span!("liabilities::construct", users = balances.len(), parts);
+
+        if parts == 0 {
+            return Err(String::from("Every balance must be split into at least one share"));
+        }
+
+        let mut leaves = Vec::new();
+        let mut salts = HashMap::new();
+
+        for (user, amount) in balances {
+            let salt = tagged_hash("newton/liability-salt", &format!("{}:{}", seed, user));
+            for (index, share) in split(amount, parts, &salt).into_iter().enumerate() {
+                leaves.push((share_id(&salt, &user, index), share, user.clone()));
+            }
+            if salts.insert(user.clone(), salt).is_some() {
+                return Err(format!("User {} appears more than once", user));
+            }
+        }
+
+        leaves.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut shares: HashMap<String, (String, Vec<usize>)> = salts.into_iter()
+            .map(|(user, salt)| (user, (salt, Vec::new())))
+            .collect();
+        for (position, (_, _, user)) in leaves.iter().enumerate() {
+            if let Some((_, indices)) = shares.get_mut(user) {
+                indices.push(position);
+            }
+        }
+
+        leaves.iter().try_fold(0u64, |total, &(_, amount, _)| total.checked_add(amount))
+            .ok_or_else(|| String::from("The total of the leaf amounts overflows"))?;
+
+        // Every offset but the last is random, and the last cancels out the others.
+        let mut offsets: Vec<u64> = leaves.iter().map(|(id, _, _)| {
+            let digest = tagged_hash("newton/liability-blinding", &format!("{}:{}", seed, id));
+            u64::from_str_radix(&digest[..16], 16).unwrap_or(0)
+        }).collect();
+        if let Some((last, others)) = offsets.split_last_mut() {
+            *last = others.iter().fold(0u64, |sum, offset| sum.wrapping_add(*offset)).wrapping_neg();
+        }
+
+        let leaves: Vec<(String, u64, u64)> = leaves.into_iter().zip(offsets)
+            .map(|((id, amount, _), blinding)| (id, amount, blinding))
+            .collect();
+        let tree = MerkleSumTree::construct_with(
+            leaves.iter().map(|&(ref id, amount, blinding)| (id.clone(), amount.wrapping_add(blinding))).collect(),
+            SumMode::Blinded
+        )?;
+
+        Ok(Liabilities { tree, leaves, shares })
+    }
+
+    /**
+     * Returns the root of the tree. Its hash and sum are what the custodian publishes.
+     */
+    pub fn root(&self) -> &SumNode {
+        self.tree.root()
+    }
+
+    /**
+     * Returns the total of every user's balance.
+     */
+    pub fn total(&self) -> u64 {
+        self.tree.total()
+    }
+
+    pub fn tree(&self) -> &MerkleSumTree {
+        &self.tree
+    }
+
+    /**
+     * Exports the `LiabilityProof` for `user`, or `None` if `user` has no balance in the tree.
+     */
+    pub fn proof(&self, user: &str) -> Option<LiabilityProof> {
+
+        let (salt, indices) = self.shares.get(user)?;
+
+        let mut shares = vec!(None; indices.len());
+        for &position in indices {
+            let (ref id, amount, blinding) = self.leaves[position];
+            let index = (0..indices.len()).find(|&index| share_id(salt, user, index) == *id)?;
+            shares[index] = Some(ShareProof { amount, blinding, proof: self.tree.prove(position)? });
+        }
+
+        let shares: Vec<ShareProof> = shares.into_iter().collect::<Option<_>>()?;
+        let amount = shares.iter().map(|share| share.amount).sum();
+
+        Some(LiabilityProof { user: String::from(user), amount, salt: salt.clone(), shares })
+    }
+}
+
+impl LiabilityProof {
+
+    /**
+     * Checks that every share of `self.amount` is a distinct leaf of the tree with root hash
+     * `root` and total `total`, and that each share's id is derived from `self.user` and
+     * `self.salt`, so no other user can be handed the same leaf. The user should also check that
+     * `self.amount` is the balance they are owed.
+     */
+    pub fn verify(&self, root: &SumHash, total: u64) -> bool {
+
+        let root = SumNode { hash: root.clone(), sum: total };
+        let mut positions: Vec<usize> = self.shares.iter().map(|share| share.proof.index).collect();
+        positions.sort();
+        positions.dedup();
+
+        let amount = self.shares.iter().try_fold(0u64, |sum, share| sum.checked_add(share.amount));
+
+        !self.shares.is_empty() &&
+        positions.len() == self.shares.len() &&
+        amount == Some(self.amount) &&
+        self.shares.iter().enumerate().all(|(index, share)| {
+            let id = share_id(&self.salt, &self.user, index);
+            share.proof.verify_with(&root, &id, share.amount.wrapping_add(share.blinding), SumMode::Blinded)
+        })
+    }
+}
+
+/*
+ * Returns the id of share `index` of `user`'s balance.
+ */
+fn share_id(salt: &str, user: &str, index: usize) -> String {
+    tagged_hash("newton/liability-share", &format!("{}:{}:{}", salt, user, index))
+}
+
+/*
+ * Splits `amount` into `parts` shares at cut points derived from `salt`.
+ */
+fn split(amount: u64, parts: usize, salt: &str) -> Vec<u64> {
+
+    let mut cuts: Vec<u64> = (1..parts).map(|index| {
+        let digest = tagged_hash("newton/liability-cut", &format!("{}:{}", salt, index));
+        let random = u64::from_str_radix(&digest[..16], 16).unwrap_or(0);
+        (u128::from(random) % (u128::from(amount) + 1)) as u64
+    }).collect();
+    cuts.push(0);
+    cuts.push(amount);
+    cuts.sort();
+
+    cuts.windows(2).map(|pair| pair[1] - pair[0]).collect()
+}
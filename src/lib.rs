@@ -4,7 +4,14 @@
 //! 
 //! ### Supported 
 //! - Merkle Trees
+//! - Key-Value Merkle Maps
 //! - Hash Pointers
+//! - Time-stamped Notarization
+//! - Chunked Blob Hashing
+//! - BitTorrent v2 Piece Hashing
+//! - Set Reconciliation with Invertible Bloom Lookup Tables
+//! - Cuckoo Filters
+//! - Merkle-Sum Trees and Proof of Liabilities
 //! 
 //! ### Planned 
 //! - Fast Fourier Transform
@@ -15,9 +22,43 @@
 #![allow(dead_code)]
 
 extern crate crypto;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 
+/*
+ * Enters a `tracing` span for the rest of the enclosing block. Expands to nothing unless the
+ * `tracing` feature is enabled.
+ */
+macro_rules! trace_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::debug_span!($($arg)*).entered();
+    }
+}
+
+/*
+ * Emits a `tracing` event. Expands to nothing unless the `tracing` feature is enabled.
+ */
+macro_rules! trace_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!($($arg)*);
+    }
+}
+
+pub mod blob;
+pub mod filter;
 pub mod hash;
+pub mod iblt;
+pub mod liabilities;
+pub mod map;
 pub mod merkle;
+pub mod notary;
+pub mod progress;
+pub mod state;
+pub mod sum;
+pub mod testvectors;
+pub mod torrent;
 
 #[cfg(test)]
 mod test;
@@ -0,0 +1,191 @@
+/*!
+ * A key-value Merkle map. Currently supports:
+ * - Construction from a vector of `(key, value)` pairs
+ * - `O(log n)` lookups by key
+ * - Inserting or replacing entries
+ * - Proofs that a key maps to a given value in the map with a given root
+ *
+ * Each leaf of the underlying `MerkleTree` is a `MapEntry`, whose hash commits to both the key
+ * and the value. Entries are sorted by key, and each key may only appear once.
+ *
+ * # Examples
+ *
+ * ```
+ * use newton::map::MerkleMap;
+ *
+ * let entries = vec!(
+ *     (String::from("alice"), String::from("10")),
+ *     (String::from("bob"), String::from("20"))
+ * );
+ * let map = MerkleMap::construct(entries).unwrap();
+ *
+ * let (value, proof) = map.get_with_proof(&String::from("bob")).unwrap();
+ * assert!(MerkleMap::verify(map.root(), &String::from("bob"), value, &proof));
+ * ```
+ */
+
+use std::cmp::Ordering;
+
+use hash::{tagged_hash, Hashable, MerkleRoot};
+use merkle::MerkleTree;
+
+/**
+ * A single `(key, value)` pair stored in the leaf of a `MerkleMap`.
+ *
+ * Entries are ordered by key, then by the hash of their value, so two entries are only equal if
+ * both their keys and their values match.
+ */
+#[derive(Clone, Debug)]
+pub struct MapEntry<K, V> {
+    key: K,
+    value: V,
+    value_hash: String
+}
+
+/**
+ * A Merkle tree whose leaves commit to `(key, value)` pairs.
+ */
+pub struct MerkleMap<K: Hashable + Ord + Clone, V: Hashable + Clone> {
+    tree: MerkleTree<MapEntry<K, V>>
+}
+
+impl<K: Hashable, V: Hashable> MapEntry<K, V> {
+
+    pub fn new(key: K, value: V) -> Self {
+        let value_hash = value.get_hash();
+        MapEntry { key, value, value_hash }
+    }
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn value(&self) -> &V {
+        &self.value
+    }
+}
+
+/**
+ * Computes sha2("newton/map-entry:" || h(key) || ":" || h(value)).
+ */
+impl<K: Hashable, V: Hashable> Hashable for MapEntry<K, V> {
+    fn get_hash(&self) -> String {
+        tagged_hash("newton/map-entry", &format!("{}:{}", self.key.get_hash(), self.value_hash))
+    }
+}
+
+impl<K: Ord, V> PartialEq for MapEntry<K, V> {
+    fn eq(&self, other: &MapEntry<K, V>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, V> Eq for MapEntry<K, V> {}
+
+impl<K: Ord, V> PartialOrd for MapEntry<K, V> {
+    fn partial_cmp(&self, other: &MapEntry<K, V>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V> Ord for MapEntry<K, V> {
+    fn cmp(&self, other: &MapEntry<K, V>) -> Ordering {
+        self.key.cmp(&other.key).then_with(|| self.value_hash.cmp(&other.value_hash))
+    }
+}
+
+impl<K: Hashable + Ord + Clone, V: Hashable + Clone> MerkleMap<K, V> {
+
+    /**
+     * Constructs a `MerkleMap` instance from `(key, value)` pairs.
+     *
+     * # Errors
+     * Will return an error if `entries` is empty or if a key appears more than once.
+     */
+    pub fn construct(entries: Vec<(K, V)>) -> Result<Self, String> {
+
+        let mut entries: Vec<MapEntry<K, V>> = entries.into_iter()
+            .map(|(key, value)| MapEntry::new(key, value))
+            .collect();
+        entries.sort();
+
+        if entries.windows(2).any(|pair| pair[0].key == pair[1].key) {
+            return Err(String::from("Each key may only appear once in a Merkle map"));
+        }
+
+        Ok(MerkleMap { tree: MerkleTree::construct(entries)? })
+    }
+
+    /**
+     * Stores `value` under `key`, replacing any value already stored there.
+     *
+     * *Note*: Replacing the value of an existing key only rehashes the path to its entry, which takes
+     * `O(log n)` time. Inserting a new key rebuilds the underlying `MerkleTree` from scratch, which takes
+     * `O(n log n)` time, so a batch of new keys is better passed to `MerkleMap::construct`.
+     *
+     * # Return Value
+     * Returns the value previously stored under `key`, if any.
+     */
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+
+        let entry = MapEntry::new(key, value);
+
+        if let Some(previous) = self.tree.replace_by(|other| other.key.cmp(&entry.key), entry.clone()) {
+            return Some(previous.value);
+        }
+
+        let mut entries: Vec<MapEntry<K, V>> = self.tree.leaves().into_iter().cloned().collect();
+        let index = entries.binary_search_by(|other| other.key.cmp(&entry.key)).unwrap_or_else(|index| index);
+        entries.insert(index, entry);
+
+        self.tree = MerkleTree::construct(entries).expect("A Merkle map always has at least one entry");
+        None
+    }
+
+    /**
+     * Returns the value stored under `key`, or `None` if there is no such key.
+     */
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.find(key).map(|entry| &entry.value)
+    }
+
+    /**
+     * Returns the value stored under `key` together with a proof that `key` maps to that value.
+     * The proof is a copy of the map's tree pruned down to that single entry, built from only the path
+     * to the entry with `MerkleTree::prove`, and can be checked with `MerkleMap::verify`.
+     *
+     * # Return Value
+     * Returns `None` if there is no such key.
+     */
+    pub fn get_with_proof(&self, key: &K) -> Option<(&V, MerkleTree<MapEntry<K, V>>)> {
+
+        let entry = self.find(key)?;
+        let proof = self.tree.prove(entry)?;
+
+        Some((&entry.value, proof))
+    }
+
+    /**
+     * Checks that `proof` shows `key` mapping to `value` in the map with root `root`.
+     */
+    pub fn verify(root: &MerkleRoot, key: &K, value: &V, proof: &MerkleTree<MapEntry<K, V>>) -> bool {
+        proof.root() == root &&
+        proof.validate_pruned().is_valid() &&
+        proof.contains(&MapEntry::new(key.clone(), value.clone())) == Ok(true)
+    }
+
+    pub fn root(&self) -> &MerkleRoot {
+        self.tree.root()
+    }
+
+    /**
+     * Returns the underlying `MerkleTree` of the map.
+     */
+    pub fn tree(&self) -> &MerkleTree<MapEntry<K, V>> {
+        &self.tree
+    }
+
+    fn find(&self, key: &K) -> Option<&MapEntry<K, V>> {
+        self.tree.find_by(|entry| entry.key.cmp(key)).ok().flatten()
+    }
+}
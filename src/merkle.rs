@@ -1,33 +1,53 @@
 /*!
  * A Merkle Tree implementation. Currently supports:
- * - Construction from a vector of objects
- * - `O(log n)` containment checks
- * - Pruning
- * - Validation and pruned validation
+ * - Construction from a vector of objects, with optional progress reporting and cancellation
+ * - Construction from precomputed leaf hashes
+ * - `O(log n)` containment checks and searches by comparator
+ * - Listing the items of every leaf
+ * - Pruning, proofs for a single item or a leaf by index, and grafting pruned branches back in
+ * - Subtree lookup and extraction by `mrkl_root`
+ * - Structural equality, root equality and finding the first differing leaf
+ * - Validation and pruned validation, stopping at the first problem or reporting every problem
+ * - Repairing corrupted hashes from known good copies of the leaves
+ * - Ordered, sorted-pair (commutative) or OpenZeppelin compatible hashing of sibling nodes
+ * - Sibling hashes for a single item, in the form OpenZeppelin's `MerkleProof` takes them
+ * - Recomputing a root from leaf hashes with `RootBuilder`
  *
  * # Errors
- * Constructing a Merkle Tree using `MerkleTree::construct(&mut Vec<T>)` will return
- * an error result if the passed vector has fewer than two items.
+ * Constructing a Merkle Tree using `MerkleTree::construct(Vec<T>)` will return
+ * an error result if the passed vector is empty.
  *
  * # Panics
  * - In non-release builds, constructing a Merkle Tree will panic if we call the constructor
- * with a vector of fewer than two elements.
+ *   with an empty vector.
  *
  * # Examples
  *
  * ```
- * let data = vec!("some", "sample", "data");
- * let mrkl_tree = merkle::MerkleTree::construct(&mut data);
+ * use newton::merkle;
+ *
+ * let data = vec!(String::from("some"), String::from("sample"), String::from("data"));
+ * let mrkl_tree = merkle::MerkleTree::construct(data).unwrap();
  * match mrkl_tree.validate() {
  *     merkle::MrklVR::Valid => {}
- *     _ => assert!(false)
+ *     _ => panic!("Freshly constructed tree should be valid")
  * }
  *
  * ```
  *
  */
 
-use hash::{Hashable, HashPointer};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+use crypto::digest::Digest;
+use crypto::sha3::Sha3;
+
+use hash::{Hashable, HashPointer, LeafHash, MerkleRoot};
+use progress::{self, ProgressHandle};
 use self::{
     MrklVR::*,
     MerkleBranch::*
@@ -39,7 +59,7 @@ use self::{
  * When a child contains another `MerkleTree`, it is specified as `MerkleBranch::Branch`.
  *
  * When a child is a leaf, it is specified as `MerkleBranch::Leaf`. Leaves contain
- * an object of type `T` and a `String` which is the sha2 hash of that object.
+ * an object of type `T` and a `String` which is the `LeafHash` of that object.
  *
  * If a child is `MerkleBranch::Partial`, we are dealing with a pruned tree.
  * `MerkleTree::validate` will never return `Valid` for a Merkle tree with
@@ -47,6 +67,7 @@ use self::{
  *
  * A child can also be `MerkleBranch::None`, if it contains no information!
  */
+#[derive(Clone)]
 enum MerkleBranch<T : Hashable + Ord + Clone> {
     Branch(Box<MerkleTree<T>>),
     Leaf(HashPointer<T>),
@@ -54,6 +75,152 @@ enum MerkleBranch<T : Hashable + Ord + Clone> {
     Empty
 }
 
+impl<T: Hashable + Ord + Clone> MerkleBranch<T> {
+
+    /**
+     * Returns the hash this branch contributes to its parent's `mrkl_root`, or `None`
+     * if the branch is `Empty`.
+     */
+    fn hash(&self) -> Option<&str> {
+        match self {
+            Branch(node) => Some(node.mrkl_root.as_str()),
+            Leaf(hpointer) => Some(&hpointer.hash),
+            Partial(hash) => Some(hash),
+            Empty => None
+        }
+    }
+}
+
+/*
+ * Branches are equal if they have the same kind and hold the same data.
+ */
+impl<T: Hashable + Ord + Clone> PartialEq for MerkleBranch<T> {
+    fn eq(&self, other: &MerkleBranch<T>) -> bool {
+        match (self, other) {
+            (Branch(a), Branch(b)) => a == b,
+            (Leaf(a), Leaf(b)) => a.hash == b.hash && a.ptr == b.ptr,
+            (Partial(a), Partial(b)) => a == b,
+            (Empty, Empty) => true,
+            _ => false
+        }
+    }
+}
+
+/**
+ * Enumerates the ways in which a `MerkleTree` combines the hashes of a node's two children
+ * into that node's `mrkl_root`.
+ *
+ * `Ordered` hashes the children in tree order: h(left || right). This is the default.
+ *
+ * `SortedPair` hashes the lexicographically smaller child hash first: h(min(left, right) || max(left, right)).
+ * A verifier then only needs the sibling hashes along a path, not which side each sibling was on. The
+ * hashes are still the domain-tagged sha2 hex digests used everywhere else in this crate.
+ *
+ * `OpenZeppelin` matches OpenZeppelin's `MerkleProof` and `StandardMerkleTree`, so roots and proofs can be
+ * checked by Solidity contracts. Parents are keccak256(min(left, right) || max(left, right)) over the raw
+ * 32 byte child hashes, with no domain tag, and a node with a single child takes that child's hash as is.
+ * Each leaf is keccak256(keccak256(stream)), where stream is what the item feeds to `Hashable::hash_into`.
+ * For a `Vec<u8>` holding `abi.encode(values)`, this is the leaf `StandardMerkleTree` computes for `values`.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PairHashing {
+    Ordered,
+    SortedPair,
+    OpenZeppelin
+}
+
+impl PairHashing {
+
+    /**
+     * Computes the `mrkl_root` of a node whose children hash to `left` and `right`. A node
+     * with a single child hashes that child on its own, except under `OpenZeppelin`, where
+     * it takes that child's hash as is.
+     */
+    pub fn combine(self, left: &str, right: Option<&str>) -> MerkleRoot {
+        if self == PairHashing::OpenZeppelin {
+            return match right {
+                Some(right) => MerkleRoot::from_hex(PairHashing::keccak_pair(left, right)),
+                None => MerkleRoot::from_hex(left.to_owned())
+            };
+        }
+
+        let mut hash = String::new();
+        match right {
+            Some(right) if self == PairHashing::SortedPair && right < left => {
+                hash.push_str(right);
+                hash.push_str(left);
+            }
+            Some(right) => {
+                hash.push_str(left);
+                hash.push_str(right);
+            }
+            None => hash.push_str(left)
+        }
+        MerkleRoot::tagged(&hash)
+    }
+
+    /**
+     * Computes the hash a `MerkleTree` built with this mode stores in the leaf holding `item`. This is
+     * `LeafHash::of(item)`, except under `OpenZeppelin`. See `PairHashing`.
+     */
+    pub fn leaf<T: Hashable + ?Sized>(self, item: &T) -> LeafHash {
+        match self {
+            PairHashing::Ordered | PairHashing::SortedPair => LeafHash::of(item),
+            PairHashing::OpenZeppelin => {
+                let mut inner = [0u8; 32];
+                let mut hasher = Sha3::keccak256();
+                item.hash_into(&mut hasher);
+                hasher.result(&mut inner);
+
+                let mut hasher = Sha3::keccak256();
+                hasher.input(&inner);
+                LeafHash::from_hex(hasher.result_str())
+            }
+        }
+    }
+
+    /**
+     * Recomputes a root from a leaf hash and the hashes of its siblings from the leaf up, as returned
+     * by `MerkleTree::proof_hashes`. This is OpenZeppelin's `MerkleProof.processProof`, so it also accepts
+     * proofs made by OpenZeppelin's tools.
+     *
+     * *Note*: Nodes with a single child contribute no sibling. Only `OpenZeppelin` passes such nodes up
+     * unchanged, so under the other modes this only gives the root if every node on the path has two children.
+     */
+    pub fn process_proof(self, leaf: &LeafHash, proof: &[String]) -> MerkleRoot {
+        proof.iter().fold(MerkleRoot::from_hex(leaf.as_str().to_owned()), |hash, sibling| {
+            self.combine(hash.as_str(), Some(sibling))
+        })
+    }
+
+    /*
+     * keccak256(min(a, b) || max(a, b)) over the raw bytes of two hex digests. Hashes which are not
+     * valid hex never come out of this crate; their text is hashed instead.
+     */
+    fn keccak_pair(a: &str, b: &str) -> String {
+        let (a, b) = match (decode_hex(a), decode_hex(b)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => (a.as_bytes().to_vec(), b.as_bytes().to_vec())
+        };
+        let (first, second) = if b < a { (b, a) } else { (a, b) };
+
+        let mut hasher = Sha3::keccak256();
+        hasher.input(&first);
+        hasher.input(&second);
+        hasher.result_str()
+    }
+}
+
+/*
+ * Decodes a hex string into bytes, or returns `None` if it is not valid hex.
+ */
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok())).collect()
+}
+
 /**
  * A struct representing a Merkle Tree, which may or may not be an internal node.
  *
@@ -66,21 +233,29 @@ enum MerkleBranch<T : Hashable + Ord + Clone> {
  *
  * `r_bound`: The largest element in the Merkle tree who has `right` as an ancestor
  *
- * `mrkl_root`: The hash of each of this node's children -- sha2(left.mrkl_root || right.mrkl_root).
+ * `mrkl_root`: The hash of each of this node's children -- sha2("newton/merkle-node:" || left.mrkl_root || right.mrkl_root).
  *
  * `height`: The height of the current node in the overall `MerkleTree`. Leaves have height 0.
+ *
+ * `pair_hashing`: How the hashes of `left` and `right` are combined into `mrkl_root`.
  */
+#[derive(Clone)]
 pub struct MerkleTree<T : Hashable + Ord + Clone> {
 
     left: MerkleBranch<T>,
     right: MerkleBranch<T>,
 
-    l_bound: T, //#####################################################
-    r_bound: T, // TODO: Pruning is worthless if we still have copies. Make Option<T>
+    // Pruning keeps both bounds, since `contains`, `prove` and `graft` search by them, so a pruned tree
+    // still holds a copy of the largest item on each side of every node left in it, even where that item
+    // was pruned away. TODO: Keep only the bounds of unpruned children, and search pruned ones by hash.
+    l_bound: T,
+    r_bound: T,
+
+    mrkl_root: MerkleRoot,
 
-    mrkl_root: String,
+    height: usize,
 
-    height: usize
+    pair_hashing: PairHashing
 }
 
 /**
@@ -95,13 +270,296 @@ pub struct MerkleTree<T : Hashable + Ord + Clone> {
  * `InvalidTree` represents a situation where the given `MerkleTree` is malformed. For example,
  * its left child is a leaf and its right child is a branch.
  *
- * `InvalidHash` and `InvalidTree` will both contain a `String` which gives more information
- * on how the validation failed.
+ * `InvalidOrder` represents a situation where the leaves of the `MerkleTree` are not sorted, or
+ * where a node's `l_bound` or `r_bound` does not match its children. `contains` and `prune`
+ * rely on this ordering.
+ *
+ * `InvalidHash`, `InvalidTree` and `InvalidOrder` will all contain a `ValidationError` which
+ * describes where and how the validation failed.
+ *
+ * A `MrklVR` converts into a `Result<(), ValidationError>`, so validation can be used with `?`.
  */
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum MrklVR {
     Valid,
-    InvalidHash(String), //String values contain an error message with a description
-    InvalidTree(String)  //of what went wrong
+    InvalidHash(ValidationError),
+    InvalidTree(ValidationError),
+    InvalidOrder(ValidationError)
+}
+
+/**
+ * Which child of a node to descend into.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Side {
+    Left,
+    Right
+}
+
+/**
+ * Machine-readable codes for each way validation can fail. The numeric values are stable
+ * and can be obtained with `ErrorCode::as_u16`.
+ *
+ * - `RootMismatch`: A node's children do not hash to its `mrkl_root`.
+ * - `LeafHashMismatch`: A leaf's item does not hash to the leaf's stored hash.
+ * - `HeightMismatch`: A node's height is not one more than its children's heights, or a fringe
+ *   node's height is not 0.
+ * - `UnexpectedPartial`: `validate` found a pruned branch. Use `validate_pruned` instead.
+ * - `DoublePruned`: Both children of a node are pruned.
+ * - `PrunedOnlyChild`: A node has a pruned child and an empty child.
+ * - `Malformed`: A node's children have incompatible kinds, for example a leaf and a branch.
+ * - `BoundMismatch`: A node's `l_bound` or `r_bound` does not match its children.
+ * - `OutOfOrder`: A node's left child holds an item greater than an item of its right child.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    RootMismatch = 1,
+    LeafHashMismatch = 2,
+    HeightMismatch = 3,
+    UnexpectedPartial = 4,
+    DoublePruned = 5,
+    PrunedOnlyChild = 6,
+    Malformed = 7,
+    BoundMismatch = 8,
+    OutOfOrder = 9
+}
+
+/**
+ * Describes a failed validation.
+ *
+ * # Fields
+ * `code`: What went wrong.
+ *
+ * `path`: The path from the root of the validated tree to the failing node. For `LeafHashMismatch`
+ * the path ends with the side of the failing leaf.
+ *
+ * `height`: The height of the failing node. For `LeafHashMismatch` this is the height of the
+ * fringe node holding the leaf, which is 0 in a well-formed tree.
+ *
+ * `message`: A human readable description of the failure.
+ *
+ * `expected` and `actual`: For `RootMismatch` and `LeafHashMismatch`, the hash recomputed from the
+ * node's children or the leaf's item, and the hash that was stored in the tree. `None` otherwise.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    pub code: ErrorCode,
+    pub path: Vec<Side>,
+    pub height: usize,
+    pub message: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>
+}
+
+/**
+ * The result of `MerkleTree::validate_full`, listing every problem found in a tree rather than
+ * only the first one.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub problems: Vec<ValidationError>
+}
+
+impl ErrorCode {
+    pub fn as_u16(self) -> u16 {
+        self as u16
+    }
+}
+
+impl ValidationError {
+    fn new(code: ErrorCode, height: usize, message: &str) -> Self {
+        ValidationError {
+            code,
+            path: Vec::new(),
+            height,
+            message: String::from(message),
+            expected: None,
+            actual: None
+        }
+    }
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (code {}, height {}, path {:?})", self.message, self.code.as_u16(), self.height, self.path)
+    }
+}
+
+impl Error for ValidationError {}
+
+impl MrklVR {
+
+    pub fn is_valid(&self) -> bool {
+        *self == Valid
+    }
+
+    /**
+     * Builds the result for a failure with the given code, picking the variant that matches it.
+     */
+    fn failure(code: ErrorCode, height: usize, message: &str) -> MrklVR {
+        let error = ValidationError::new(code, height, message);
+        match code {
+            ErrorCode::RootMismatch | ErrorCode::LeafHashMismatch => InvalidHash(error),
+            ErrorCode::BoundMismatch | ErrorCode::OutOfOrder => InvalidOrder(error),
+            _ => InvalidTree(error)
+        }
+    }
+
+    /**
+     * Attaches the recomputed and stored hashes to a failure. `Valid` results are returned unchanged.
+     */
+    fn with_hashes(mut self, expected: &str, actual: &str) -> MrklVR {
+        match self {
+            InvalidHash(ref mut error) | InvalidTree(ref mut error) | InvalidOrder(ref mut error) => {
+                error.expected = Some(String::from(expected));
+                error.actual = Some(String::from(actual));
+            }
+            Valid => {}
+        }
+        self
+    }
+
+    /**
+     * Records that this result came from the child on `side`, by prepending `side` to the path
+     * of a failure. `Valid` results are returned unchanged.
+     */
+    fn within(mut self, side: Side) -> MrklVR {
+        match self {
+            InvalidHash(ref mut error) | InvalidTree(ref mut error) | InvalidOrder(ref mut error) => {
+                error.path.insert(0, side);
+            }
+            Valid => {}
+        }
+        self
+    }
+}
+
+impl From<MrklVR> for Result<(), ValidationError> {
+    fn from(result: MrklVR) -> Self {
+        match result {
+            Valid => Ok(()),
+            InvalidHash(error) | InvalidTree(error) | InvalidOrder(error) => Err(error)
+        }
+    }
+}
+
+/**
+ * The reasons `MerkleTree::prune` can fail.
+ *
+ * `InvalidTree` is returned when the tree does not pass `MerkleTree::validate`. It contains the
+ * `ValidationError` of the failed validation. Trees that have already been pruned cannot be pruned again.
+ *
+ * `NothingToKeep` is returned when `to_keep` is empty.
+ *
+ * `MissingItems` is returned when some elements of `to_keep` are not leaves of the tree. It lists
+ * every such element. Pruning to a set containing them would produce a tree that proves nothing
+ * about them.
+ *
+ * `Malformed` is returned when pruning fails partway through. In this case the tree may have been
+ * partially pruned.
+ *
+ * The tree is left untouched in every case except `Malformed`.
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub enum PruneError<T> {
+    InvalidTree(ValidationError),
+    NothingToKeep,
+    MissingItems(Vec<T>),
+    Malformed
+}
+
+/**
+ * The reasons `MerkleTree::graft` and `MerkleTree::graft_leaf` can fail.
+ *
+ * `InvalidSubtree` is returned when the subtree does not pass `MerkleTree::validate_pruned`. It contains
+ * the `ValidationError` of the failed validation.
+ *
+ * `PairHashingMismatch` is returned when the subtree was constructed with a different `PairHashing` mode.
+ *
+ * `NoPrunedBranch` is returned when there is no pruned branch where the subtree or leaf belongs.
+ *
+ * `HashMismatch` is returned when the subtree's `mrkl_root`, or the leaf's hash, differs from the hash
+ * stored in place of the pruned branch.
+ *
+ * `HeightMismatch` is returned when the subtree has a different height than the pruned branch.
+ *
+ * The tree is left untouched in every case.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GraftError {
+    InvalidSubtree(ValidationError),
+    PairHashingMismatch,
+    NoPrunedBranch,
+    HashMismatch,
+    HeightMismatch
+}
+
+impl MerkleTree<LeafHash> {
+
+    /**
+     * Constructs a `MerkleTree` directly from leaf hashes which were computed elsewhere, for example
+     * with `LeafHash::of` over transactions read from a database, without hashing any items. The hashes are
+     * stored as they are, so they must already be tagged leaf hashes. The root is the same as that of a tree
+     * built from the items themselves whenever the items sort in the same order as their hashes.
+     *
+     * *Note*: Like every `MerkleTree`, the leaves are sorted, here by hash, so the order of `hashes` is not
+     * kept. Indexes passed to `prove_leaf` count leaves in sorted hash order: index `i` is the `i`-th
+     * smallest hash, not `hashes[i]`. Use `prove` to prove a given hash without knowing its index.
+     *
+     * # Errors
+     * Will return an error result if `hashes` is empty.
+     */
+    pub fn from_leaf_hashes(hashes: Vec<LeafHash>, pair_hashing: PairHashing) -> Result<Self, String> {
+        MerkleTree::construct_with(hashes, pair_hashing)
+    }
+}
+
+/**
+ * Two `MerkleTree`s are equal if they have exactly the same structure: the same leaves, the same pruned
+ * branches, the same bounds and heights, and the same `PairHashing` mode. Use `MerkleTree::same_root` to
+ * only compare roots.
+ */
+impl<T: Hashable + Ord + Clone> PartialEq for MerkleTree<T> {
+    fn eq(&self, other: &MerkleTree<T>) -> bool {
+        self.mrkl_root == other.mrkl_root &&
+        self.height == other.height &&
+        self.pair_hashing == other.pair_hashing &&
+        self.l_bound == other.l_bound &&
+        self.r_bound == other.r_bound &&
+        self.left == other.left &&
+        self.right == other.right
+    }
+}
+
+impl<T: Hashable + Ord + Clone> Eq for MerkleTree<T> {}
+
+/*
+ * Drops the nodes below this one from an explicit stack, rather than recursing once per level,
+ * so dropping a very deep tree cannot overflow the stack.
+ */
+impl<T: Hashable + Ord + Clone> Drop for MerkleTree<T> {
+    fn drop(&mut self) {
+
+        let mut stack = Vec::new();
+        let take_branches = |node: &mut MerkleTree<T>, stack: &mut Vec<Box<MerkleTree<T>>>| {
+            for branch in &mut [&mut node.left, &mut node.right] {
+                if let Branch(child) = ::std::mem::replace(*branch, Empty) {
+                    stack.push(child);
+                }
+            }
+        };
+
+        take_branches(self, &mut stack);
+        while let Some(mut node) = stack.pop() {
+            take_branches(&mut node, &mut stack);
+        }
+    }
 }
 
 impl<T: Hashable + Ord + Clone> MerkleTree<T> {
@@ -112,8 +570,9 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
      *
      * # Arguments
      * - `data`: A vector of data which will be used to build the `MerkleTree` instance. For example, if data
-     * was `vec!(x, y, z)`, then the resulting `MerkleTree` would be
+     *   was `vec!(x, y, z)`, then the resulting `MerkleTree` would be
      *
+     * ```text
      *           h(h(h(x)||h(y))||h(h(z)))
      *               /        \
      *              /          \
@@ -124,58 +583,191 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
      *       h(x)     h(y)     h(z)
      *        |        |        |
      *        x        y        z
+     * ```
      *
      * # Panics
-     * In non-release builds, will panic if `data.len()` is less than 2.
+     * In non-release builds, will panic if `data` is empty.
+     *
+     * # Errors
+     * May return an error if it fails to construct leaves correctly.
+     * Will return an error result if `data` is empty.
+     */
+    pub fn construct(data: Vec<T>) -> Result<Self, String> {
+        MerkleTree::construct_with(data, PairHashing::Ordered)
+    }
+
+    /**
+     * Constructs a `MerkleTree` instance whose sibling hashes are combined according to
+     * `pair_hashing`. See `MerkleTree::construct` and `PairHashing`.
      *
      * # Errors
      * May return an error if it fails to construct leaves correctly.
-     * Will return an error result if the length of `data` is less than 2.
+     * Will return an error result if `data` is empty.
+     */
+    pub fn construct_with(data: Vec<T>, pair_hashing: PairHashing) -> Result<Self, String> {
+        MerkleTree::construct_with_progress(data, pair_hashing, &ProgressHandle::new())
+    }
+
+    /**
+     * Constructs a `MerkleTree` instance like `MerkleTree::construct_with`, reporting progress to
+     * `progress` after each node is built, and stopping early if `progress` is cancelled.
+     *
+     * # Errors
+     * Will return an error result if `data` is empty. If `progress` is cancelled before the tree is
+     * complete, the error is `progress::CANCELLED`.
      */
-    pub fn construct(mut data: Vec<T>) -> Result<Self, String> {
+    pub fn construct_with_progress(mut data: Vec<T>, pair_hashing: PairHashing, progress: &ProgressHandle)
+            -> Result<Self, String> {
+
+        trace_span!("merkle::construct", leaves = data.len(), ?pair_hashing);
 
         data.sort();
 
-        if data.len() < 1 {
+        if data.is_empty() {
             debug_assert!(false, "Wrong number of arguments to merkle tree constructor.");
 
             return Err(String::from(
-                "Not enough data to construct Merkle Tree. Must receive at least two items."
+                "Not enough data to construct Merkle Tree. Must receive at least one item."
             ));
         }
 
-        let mut mrkl_trees: Vec<MerkleTree<T>> = Vec::new();
+        let total = MerkleTree::<T>::node_count(data.len());
+        let mut done = 0;
+        progress.report(done, total);
+
+        // Leaves and nodes are taken from the front of each level, so hold them in a
+        // VecDeque rather than paying for `Vec::remove(0)` on every node.
+        let mut data: VecDeque<T> = data.into();
+        let mut mrkl_trees: VecDeque<MerkleTree<T>> = VecDeque::new();
 
-        while data.len() > 0 {
+        while !data.is_empty() {
 
-            let fringe_node = MerkleTree::construct_fringe_node(&mut data);
+            if progress.is_cancelled() {
+                return Err(String::from(progress::CANCELLED));
+            }
+
+            let fringe_node = MerkleTree::construct_fringe_node(&mut data, pair_hashing);
             match fringe_node {
-                Ok(node) => mrkl_trees.push(node),
+                Ok(node) => mrkl_trees.push_back(node),
                 Err(msg) => { return Err(msg); }
             }
 
+            done += 1;
+            progress.report(done, total);
         }
 
         let mut height = 1;
 
         while mrkl_trees.len() > 1 {
 
-            let mut new_mrkl_trees: Vec<MerkleTree<T>> = Vec::new();
+            let mut new_mrkl_trees: VecDeque<MerkleTree<T>> = VecDeque::new();
 
-            while mrkl_trees.len() > 0 {
+            while !mrkl_trees.is_empty() {
+
+                if progress.is_cancelled() {
+                    return Err(String::from(progress::CANCELLED));
+                }
 
-                let internal_node = MerkleTree::construct_internal_node(&mut mrkl_trees, height);
+                let internal_node = MerkleTree::construct_internal_node(&mut mrkl_trees, height, pair_hashing);
                 match internal_node {
-                    Ok(node) => new_mrkl_trees.push(node),
+                    Ok(node) => new_mrkl_trees.push_back(node),
                     Err(msg) => { return Err(msg); }
                 }
 
+                done += 1;
+                progress.report(done, total);
             }
 
             mrkl_trees = new_mrkl_trees;
             height += 1;
         }
-        Ok(mrkl_trees.remove(0))
+
+        trace_event!(height = height - 1, "constructed tree");
+        mrkl_trees.pop_front().ok_or_else(|| String::from("Construction produced no root"))
+    }
+
+    /**
+     * Generates a proof that `item` is in the tree, in the form of a copy of the tree pruned down to
+     * `item`. Only the nodes on the path to `item` are copied, and each branch next to that path is
+     * replaced by its hash, so this takes `O(log n)` time and gives the same result as cloning the tree
+     * and pruning it to `item`.
+     *
+     * *Note*: Unlike `prune`, this does not validate the tree first. A verifier should check the proof
+     * with `validate_pruned` and compare its root against a trusted root.
+     *
+     * # Return Value
+     * Returns `None` if `item` is not in the tree, or if the path to it has been pruned away.
+     */
+    pub fn prove<Q: ?Sized + Ord>(&self, item: &Q) -> Option<MerkleTree<T>> where T: Borrow<Q> {
+        self.copy_path(
+            &mut |node| Some(if item <= node.l_bound.borrow() { Side::Left } else { Side::Right }),
+            &|leaf| leaf.borrow() == item
+        )
+    }
+
+    /**
+     * Generates a proof that the leaf at `index` is in the tree, in the form of a copy of the tree
+     * pruned down to that leaf. Leaves are indexed in sorted order, as returned by `leaves` for a tree
+     * which has not been pruned. Like `prove`, this only copies the path to the leaf, and takes `O(log n)` time.
+     *
+     * The path is found from the heights of the nodes along it: the left child of a node with height `h`
+     * holds `2^h` leaves whenever the node has a right child.
+     *
+     * # Return Value
+     * Returns `None` if there is no leaf at `index`, or if the path to it has been pruned away.
+     */
+    pub fn prove_leaf(&self, index: usize) -> Option<MerkleTree<T>> {
+        let mut index = index;
+        self.copy_path(
+            &mut |node| {
+                let left_len = 1usize.checked_shl(node.height as u32).unwrap_or(usize::MAX);
+                if index < left_len {
+                    Some(Side::Left)
+                } else if index - left_len < left_len {
+                    index -= left_len;
+                    Some(Side::Right)
+                } else {
+                    None
+                }
+            },
+            &|_| true
+        )
+    }
+
+    /**
+     * Returns the hashes of the siblings along the path to `item`, from the leaf up, in the form OpenZeppelin's
+     * `MerkleProof.verify` takes as its `proof`. Nodes with a single child contribute no hash. Under
+     * `PairHashing::OpenZeppelin`, `PairHashing::process_proof` recomputes the root from these and the item's
+     * leaf hash.
+     *
+     * # Return Value
+     * Returns `None` if `item` is not in the tree, or if the path to it has been pruned away.
+     */
+    pub fn proof_hashes<Q: ?Sized + Ord>(&self, item: &Q) -> Option<Vec<String>> where T: Borrow<Q> {
+
+        let mut hashes = Vec::new();
+        let mut node = self;
+
+        loop {
+            let (side, other_side) = if item <= node.l_bound.borrow() {
+                (Side::Left, Side::Right)
+            } else {
+                (Side::Right, Side::Left)
+            };
+
+            if let Some(hash) = node.child(other_side).hash() {
+                hashes.push(hash.to_owned());
+            }
+
+            match node.child(side) {
+                Branch(child) => { node = child; }
+                Leaf(hpointer) if hpointer.ptr.as_ref().borrow() == item => { break; }
+                _ => { return None; }
+            }
+        }
+
+        hashes.reverse();
+        Some(hashes)
     }
 
     /**
@@ -190,16 +782,13 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
      * # Arguments
      * `to_keep`: An array slice which lists the leaves you wish to keep in the Merkle tree.
      *
-     * # Return Value
-     * Returns `true` if there were no errors during pruning, and `false` otherwise.
-     *
      * # Examples
      *
      * Consider the following scenario:
      *
      * Calling `prune` on the left tree with `to_keep=[y]` yields the tree on the right.
      *
-     *
+     * ```text
      *                  h3                             h3
      *                 /  \                           /  \
      *                /    \                         /    \
@@ -214,271 +803,1073 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
      *       hx      hy    hz       hw     hx       hy
      *       |       |     |        |                |
      *       x       y     z        w                y
-     *
+     * ```
      *
      * In the resulting tree, the right child of `root` and the left child of `h1` are now just hashes.
      *
      * # Errors
-     * - Will return false if `to_keep` is empty, since this would be effectively pruning the
-     * entire tree away.
-     * - There are a number of errors that could occur when pruning malformed trees, so it may be advisable
-     * to validate a tree before pruning, unless you are certain the tree is valid. One such error is that
-     * there is an empty branch as the left child.
+     * Returns a `PruneError` describing the failure. See `PruneError` for the meaning of each
+     * variant. In particular:
+     * - Will return `PruneError::NothingToKeep` if `to_keep` is empty, since this would be effectively
+     *   pruning the entire tree away.
+     * - Will return `PruneError::MissingItems` listing every element of `to_keep` which is not in the tree.
+     * - The tree must pass `validate`, so trees which were already pruned cannot be pruned again.
      *
      */
-    pub fn prune(&mut self, to_keep: &[T]) -> bool {
+    pub fn prune(&mut self, to_keep: &[T]) -> Result<(), PruneError<T>> {
+
+        trace_span!("merkle::prune", height = self.height, to_keep = to_keep.len());
 
         // The tree we are pruning must be valid. Otherwise there is
         // no way for us to check whether all the elements in `to_keep`
         // are contained within the tree, and therefore no way for us to
         // recurse properly. All the elements of the tree must be sorted as
-        // well, which is also verifed by validate.
-        if let Valid = self.validate() {} else { // Check if tree is valid
-            return false;
+        // well, which is also verified by validate.
+        match self.validate() {
+            Valid => {}
+            InvalidHash(error) | InvalidTree(error) | InvalidOrder(error) => {
+                return Err(PruneError::InvalidTree(error));
+            }
         }
 
         // We also cannot prune an entire tree. An alternative to this would
         // be to grab the `mrkl_root` from the root node.
-        if to_keep.len() <= 0 { return false; }
+        if to_keep.is_empty() { return Err(PruneError::NothingToKeep); }
 
         // All elements of `to_keep` must be contained within the Merkle tree.
         // Otherwise we would encounter situations where we do not prune a branch
         // even though it contains no leaves we wish to keep.
-        for element in to_keep {
-            if !self.contains(element).unwrap() { return false; }
+        let missing: Vec<T> = to_keep.iter()
+            .filter(|element| !self.contains(element).unwrap_or(false))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            trace_event!(missing = missing.len(), "items to keep are missing from the tree");
+            return Err(PruneError::MissingItems(missing));
         }
 
-        self._prune(to_keep)
+        if self._prune(to_keep) { Ok(()) } else { Err(PruneError::Malformed) }
 
     }
 
     /**
-     * Reports whether or not a given item is contained within one of the leaves of the Merkle tree.
-     * The merkle leaves are sorted, so this method binary searches for the correct leaf in O(log n) time.
-     *
-     * # Arguments
-     * `item`: A borrow of the item you want to search for
+     * Finds the node of this `MerkleTree` whose `mrkl_root` is `root_hash`. This may be the tree
+     * itself. Pruned branches are not searched.
      *
      * # Return Value
-     * Returns `true` if it finds a leaf in the merkle tree with data equal to `item`, and `false` otherwise.
-     *
-     * # Errors
-     * Searching for an item in a pruned tree will only work if the item was not pruned. Otherwise,
-     * There is usually no way to tell whether or not that item was ever in the tree before it was pruned.
-     * Therefore, if during the exectution of `contains` the search encounters a partial branch, it will
-     * return an error.
+     * Returns a borrow of the matching node, or `None` if there is no such node.
      */
-    pub fn contains(&self, item: &T) -> Result<bool, String> {
-        let search_branch = if *item <= self.l_bound {
-            &self.left
-        } else {
-            &self.right
-        };
+    pub fn subtree(&self, root_hash: &MerkleRoot) -> Option<&MerkleTree<T>> {
+
+        if self.mrkl_root == *root_hash {
+            return Some(self);
+        }
 
-        match search_branch {
-            Branch(node) => node.contains(item),
-            Leaf(value) => Ok(*value.ptr == *item),
-            Partial(_) => Err(String::from("Could not search further in pruned tree")),
-            _ => Ok(false)
+        for branch in &[&self.left, &self.right] {
+            if let Branch(ref node) = **branch {
+                if let Some(found) = node.subtree(root_hash) {
+                    return Some(found);
+                }
+            }
         }
+
+        None
     }
 
     /**
-     * Validates a given instance of `MerkleTree`.
+     * Removes the node of this `MerkleTree` whose `mrkl_root` is `root_hash` and returns it,
+     * leaving a partial branch holding `root_hash` in its place. The remaining tree is a
+     * pruned tree, and the extracted subtree can later be put back with `MerkleTree::graft`.
      *
      * # Return Value
-     * Returns a `MrklVR` enumeration. See the documentation for `MrklVR` for the meanings
-     * of each result.
-     *
-     * *Note*: This method will return InvalidTree if called on a pruned `MerkleTree` instance.
-     * Use `MerkleTree::validate_pruned` in those cases which validation of a pruned Merkle tree
-     * is required.
+     * Returns the extracted node, or `None` if no descendant of this tree has the given
+     * `mrkl_root`. The root of the tree itself cannot be extracted.
+     */
+    pub fn extract_subtree(&mut self, root_hash: &MerkleRoot) -> Option<MerkleTree<T>> {
+        match MerkleTree::extract_from(&mut self.left, root_hash) {
+            Some(found) => Some(found),
+            None => MerkleTree::extract_from(&mut self.right, root_hash)
+        }
+    }
+
+    /**
+     * Checks whether this tree and `other` have the same `mrkl_root`. This is much cheaper than
+     * `==`, which compares the trees structurally, and unlike `==` it considers a pruned tree
+     * equal to the full tree it was pruned from.
+     */
+    pub fn same_root(&self, other: &MerkleTree<T>) -> bool {
+        self.mrkl_root == other.mrkl_root
+    }
+
+    /**
+     * Finds the index of the first leaf at which this tree and `other` differ. Subtrees with equal
+     * hashes are skipped without being walked, so this takes `O(d log n)` time when `d` leaves differ.
+     *
+     * # Return Value
+     * Returns the index of the first differing leaf, or `None` if both trees hold the same leaves.
+     * If one tree holds a prefix of the other's leaves, the index of the first extra leaf is returned.
+     * Pruned branches are compared by hash only, so a difference inside a pruned branch is reported
+     * at the first leaf of that branch.
+     */
+    pub fn first_divergent_leaf(&self, other: &MerkleTree<T>) -> Option<usize> {
+
+        let (short, tall) = if self.height <= other.height { (self, other) } else { (other, self) };
+
+        // The leftmost node of the taller tree at the shorter tree's height covers the same
+        // leaf indices as the whole shorter tree.
+        let mut aligned = tall;
+        while aligned.height > short.height {
+            match aligned.left {
+                Branch(ref node) => { aligned = node; }
+                _ => { return Some(0); }
+            }
+        }
+
+        match short.divergence_from(aligned, 0) {
+            Some(index) => Some(index),
+            None if tall.height > short.height => Some(1 << (short.height + 1)),
+            None => None
+        }
+    }
+
+    /**
+     * Re-attaches a subtree in place of a branch that was previously pruned away. This lets the
+     * holder of a pruned tree progressively fill in data it receives later on.
+     *
+     * The pruned branch the subtree belongs in is found by binary searching for the subtree's
+     * largest element, and the subtree is only attached if its `mrkl_root` equals the hash that
+     * was stored in place of the pruned branch.
+     *
+     * # Arguments
+     * `subtree`: A `MerkleTree` which may itself be pruned, and must pass `validate_pruned`.
+     *
+     * # Errors
+     * Returns a `GraftError`, and leaves the tree untouched, if the subtree is invalid, was
+     * constructed with a different `PairHashing` mode, or does not match the pruned branch
+     * where it belongs. See `GraftError` for the meaning of each variant.
+     */
+    pub fn graft(&mut self, subtree: MerkleTree<T>) -> Result<(), GraftError> {
+
+        trace_span!("merkle::graft", height = self.height, subtree_height = subtree.height);
+
+        match subtree.validate_pruned() {
+            Valid => {}
+            InvalidHash(error) | InvalidTree(error) | InvalidOrder(error) => { return Err(GraftError::InvalidSubtree(error)); }
+        }
+
+        if subtree.pair_hashing != self.pair_hashing {
+            return Err(GraftError::PairHashingMismatch);
+        }
+
+        let key = subtree.r_bound.clone();
+        let hash = subtree.mrkl_root.as_str().to_owned();
+        let height = Some(subtree.height);
+
+        self.graft_branch(&key, &hash, height, Branch(Box::new(subtree)))
+    }
+
+    /**
+     * Re-attaches a single leaf in place of a leaf that was previously pruned away.
+     * See `MerkleTree::graft`.
+     *
+     * # Errors
+     * Returns a `GraftError`, and leaves the tree untouched, if there is no pruned leaf where the
+     * item belongs or the item's hash does not match it.
+     */
+    pub fn graft_leaf(&mut self, item: T) -> Result<(), GraftError> {
+
+        let key = item.clone();
+        let leaf = HashPointer::to_leaf(item, self.pair_hashing);
+        let hash = leaf.hash.clone();
+
+        self.graft_branch(&key, &hash, None, Leaf(leaf))
+    }
+
+    /**
+     * Returns the items of every leaf in the Merkle tree, in sorted order. Pruned branches are skipped.
+     */
+    pub fn leaves(&self) -> Vec<&T> {
+
+        let mut leaves = Vec::new();
+        let mut stack = vec!(self);
+
+        while let Some(node) = stack.pop() {
+            for branch in &[&node.left, &node.right] {
+                if let Leaf(value) = branch {
+                    leaves.push(value.ptr.as_ref());
+                }
+            }
+            for branch in &[&node.right, &node.left] {
+                if let Branch(child) = branch {
+                    stack.push(child);
+                }
+            }
+        }
+
+        leaves
+    }
+
+    /**
+     * Binary searches the leaves of the Merkle tree using a comparator function, like
+     * `slice::binary_search_by`. This allows searching for a leaf without a complete item to compare
+     * against, for example by only part of its ordering.
+     *
+     * # Arguments
+     * `f`: Returns `Less` for leaves before the one searched for, `Greater` for leaves after it, and
+     * `Equal` for the leaf itself. It must be consistent with the order of the leaves.
+     *
+     * # Return Value
+     * Returns the item of the leaf for which `f` returns `Equal`, or `None` if there is no such leaf.
+     *
+     * # Errors
+     * Like `contains`, returns an error if the search encounters a partial branch.
+     */
+    pub fn find_by<F>(&self, f: F) -> Result<Option<&T>, String> where F: Fn(&T) -> Ordering {
+        let mut node = self;
+        loop {
+            let search_branch = if f(&node.l_bound) != Ordering::Less {
+                &node.left
+            } else {
+                &node.right
+            };
+
+            match search_branch {
+                Branch(child) => node = child,
+                Leaf(value) if f(&value.ptr) == Ordering::Equal => { return Ok(Some(value.ptr.as_ref())); }
+                Partial(_) => { return Err(String::from("Could not search further in pruned tree")); }
+                _ => { return Ok(None); }
+            }
+        }
+    }
+
+    /**
+     * Replaces the item of the leaf for which `f` returns `Equal` with `item`, and recomputes the bounds and
+     * `mrkl_root` of each node on the path to that leaf, in `O(log n)` time. See `find_by` for the meaning of `f`.
+     *
+     * The caller must make sure `item` sorts into the same position as the item it replaces, for example
+     * because `f` only compares a key which is unique in the tree, and `item` has the same key.
+     *
+     * # Return Value
+     * Returns the replaced item, or `None` if `f(&item)` is not `Equal` or there is no leaf for which `f`
+     * returns `Equal`. The tree is left untouched in that case.
+     */
+    pub(crate) fn replace_by<F>(&mut self, f: F, item: T) -> Option<T> where F: Fn(&T) -> Ordering {
+        if f(&item) != Ordering::Equal || self.find_by(&f).ok()?.is_none() {
+            return None;
+        }
+        self.replace_on_path(&f, item)
+    }
+
+    /**
+     * Reports whether or not a given item is contained within one of the leaves of the Merkle tree.
+     * The merkle leaves are sorted, so this method binary searches for the correct leaf in O(log n) time.
+     *
+     * # Arguments
+     * `item`: A borrow of the item you want to search for. Like `HashMap::get`, this may be any borrowed
+     * form of `T` with the same ordering, so a `MerkleTree<String>` can be searched with a `&str`.
+     *
+     * # Return Value
+     * Returns `true` if it finds a leaf in the merkle tree with data equal to `item`, and `false` otherwise.
+     *
+     * # Errors
+     * Searching for an item in a pruned tree will only work if the item was not pruned. Otherwise,
+     * There is usually no way to tell whether or not that item was ever in the tree before it was pruned.
+     * Therefore, if during the exectution of `contains` the search encounters a partial branch, it will
+     * return an error.
+     */
+    pub fn contains<Q: ?Sized + Ord>(&self, item: &Q) -> Result<bool, String> where T: Borrow<Q> {
+
+        let mut node = self;
+
+        loop {
+            let search_branch = if item <= node.l_bound.borrow() {
+                &node.left
+            } else {
+                &node.right
+            };
+
+            match search_branch {
+                Branch(child) => { node = child; }
+                Leaf(value) => { return Ok(value.ptr.as_ref().borrow() == item); }
+                Partial(_) => { return Err(String::from("Could not search further in pruned tree")); }
+                _ => { return Ok(false); }
+            }
+        }
+    }
+
+    /**
+     * Validates a given instance of `MerkleTree`.
+     *
+     * # Return Value
+     * Returns a `MrklVR` enumeration. See the documentation for `MrklVR` for the meanings
+     * of each result.
+     *
+     * *Note*: This method will return InvalidTree if called on a pruned `MerkleTree` instance.
+     * Use `MerkleTree::validate_pruned` in those cases which validation of a pruned Merkle tree
+     * is required.
      *
      * # Panics
      * In non-release builds panics if, when validating a fringe node, it encounters a situation
      * where a right item hash is given but no right item is given, or vice versa. Note that in
      * release builds this will cause `validate` to return `MrklVR::InvalidHash`.
      */
-    pub fn validate(&self) -> MrklVR {
-        self._validate(false)
+    pub fn validate(&self) -> MrklVR {
+        trace_span!("merkle::validate", height = self.height);
+        let result = self._validate(false);
+        trace_event!(valid = result.is_valid(), "validated tree");
+        result
+    }
+
+     /**
+     * Validates a given pruned instance of `MerkleTree`.
+     *
+     * # Return Value
+     * Returns a `MrklVR` enumeration. See the documentation for `MrklVR` for the meanings
+     * of each result.
+     *
+     * # Panics
+     * In non-release builds panics if, when validating a fringe node, it encounters a situation
+     * where a right item hash is given but no right item is given, or vice versa. Note that in
+     * release builds this will cause `validate` to return `MrklVR::InvalidHash`.
+     */
+    pub fn validate_pruned(&self) -> MrklVR {
+        trace_span!("merkle::validate_pruned", height = self.height);
+        let result = self._validate(true);
+        trace_event!(valid = result.is_valid(), "validated pruned tree");
+        result
+    }
+
+    /**
+     * Validates a given instance of `MerkleTree` like `validate`, but instead of stopping at the first
+     * inconsistency, checks every node and records every problem it finds. This is useful when
+     * investigating a corrupted tree.
+     *
+     * # Return Value
+     * Returns a `ValidationReport`. Each problem records the path to and height of the bad node, and
+     * for hash mismatches both the recomputed and the stored hash.
+     *
+     * *Note*: Like `validate`, this reports every pruned branch as a problem. Use
+     * `MerkleTree::validate_full_pruned` for pruned trees.
+     */
+    pub fn validate_full(&self) -> ValidationReport {
+        trace_span!("merkle::validate_full", height = self.height);
+        let problems = self.collect_problems(false);
+        trace_event!(problems = problems.len(), "validated every node");
+        ValidationReport { problems }
+    }
+
+    /**
+     * Validates a given pruned instance of `MerkleTree` like `validate_pruned`, recording every problem
+     * it finds. See `MerkleTree::validate_full`.
+     */
+    pub fn validate_full_pruned(&self) -> ValidationReport {
+        trace_span!("merkle::validate_full_pruned", height = self.height);
+        let problems = self.collect_problems(true);
+        trace_event!(problems = problems.len(), "validated every node");
+        ValidationReport { problems }
+    }
+
+    /**
+     * Repairs the problems listed in `report`, for example hashes corrupted by bit rot on disk, without
+     * rebuilding the tree. Only the nodes flagged in `report` and their ancestors are touched.
+     *
+     * Every leaf flagged with `LeafHashMismatch` is restored from `originals`. Then the height and
+     * `mrkl_root` of every node on the path to a flagged node are recomputed from its children, deepest
+     * first. Only nodes flagged with `RootMismatch` or `HeightMismatch` may change; every other node must
+     * recompute to the `mrkl_root` and height it already has.
+     *
+     * # Arguments
+     * `report`: The problems to repair, as returned by `validate_full` or `validate_full_pruned` for this tree.
+     *
+     * `originals`: Known good copies of the items in the tree. If a flagged leaf's item is among them, the item
+     * is trusted and its stored hash is recomputed. Otherwise, if one of them has the leaf's stored hash, the
+     * item is replaced with that original.
+     *
+     * # Errors
+     * Returns a `ValidationError`, and leaves the tree untouched, if a problem cannot be repaired:
+     * - The `ValidationError` of a flagged leaf which does not match any of `originals`.
+     * - The `ValidationError` of a problem with any other code, or whose path does not lead to a node
+     *   of this tree.
+     * - A `RootMismatch` or `HeightMismatch` for a node which was not flagged but would change. This
+     *   happens when a leaf's item has been swapped for a different original, or when `report` is
+     *   out of date.
+     *
+     * *Note*: If the root itself was flagged, its `mrkl_root` is recomputed, so callers should compare
+     * it against a trusted root after repairing.
+     */
+    pub fn repair(&mut self, report: &ValidationReport, originals: &[T]) -> Result<(), ValidationError> {
+
+        trace_span!("merkle::repair", height = self.height, problems = report.problems.len(), originals = originals.len());
+
+        let originals: HashMap<String, &T> = originals.iter().map(|item| (item.leaf_hash(self.pair_hashing).into_string(), item)).collect();
+        let mut leaves = Vec::new();
+        let mut flagged = Vec::new();
+
+        for problem in &report.problems {
+            let path = &problem.path[..];
+            match problem.code {
+                ErrorCode::LeafHashMismatch => {
+                    let leaf = self.repaired_leaf(path, &originals).ok_or_else(|| problem.clone())?;
+                    leaves.push((path, leaf));
+                }
+                ErrorCode::RootMismatch | ErrorCode::HeightMismatch if self.node_at(path).is_some() => {
+                    flagged.push((path, problem.code));
+                }
+                _ => { return Err(problem.clone()); }
+            }
+        }
+
+        let mut paths: Vec<&[Side]> = leaves.iter().map(|&(path, _)| &path[..path.len() - 1])
+            .chain(flagged.iter().map(|&(path, _)| path))
+            .flat_map(|path| (0..=path.len()).map(move |len| &path[..len]))
+            .collect();
+        paths.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        paths.dedup();
+
+        let mut updates: Vec<(&[Side], usize, MerkleRoot)> = Vec::new();
+        for path in paths {
+            let (height, root) = self.recompute_node(path, &leaves, &updates);
+            let node = self.node_at(path).expect("Every repaired path leads to a node");
+
+            for &(code, changed) in &[(ErrorCode::RootMismatch, root != node.mrkl_root),
+                                      (ErrorCode::HeightMismatch, height != node.height)] {
+                if changed && !flagged.contains(&(path, code)) {
+                    let mut error = ValidationError::new(code, node.height, "Repairing would change a node which was not reported");
+                    error.path = path.to_vec();
+                    if code == ErrorCode::RootMismatch {
+                        error.expected = Some(String::from(root.as_str()));
+                        error.actual = Some(String::from(node.mrkl_root.as_str()));
+                    }
+                    return Err(error);
+                }
+            }
+
+            updates.push((path, height, root));
+        }
+
+        trace_event!(leaves = leaves.len(), nodes = updates.len(), "repaired tree");
+
+        for (path, leaf) in leaves {
+            let (&side, parent) = path.split_last().expect("Every leaf has a parent");
+            *self.node_at_mut(parent).expect("Every repaired path leads to a node").child_mut(side) = Leaf(leaf);
+        }
+        for (path, height, root) in updates {
+            let node = self.node_at_mut(path).expect("Every repaired path leads to a node");
+            node.height = height;
+            node.mrkl_root = root;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Returns the `mrkl_root` of this `MerkleTree`.
+     */
+    pub fn root(&self) -> &MerkleRoot {
+        &self.mrkl_root
+    }
+
+    /**
+     * Returns the `PairHashing` mode this `MerkleTree` was constructed with.
+     */
+    pub fn pair_hashing(&self) -> PairHashing {
+        self.pair_hashing
+    }
+
+    /*
+    --------------------------------------------------------------------------------------------------------
+    |                                      Private pruning methods                                         |
+    --------------------------------------------------------------------------------------------------------
+    */
+
+    /**
+     * Helper function that does the heavy lifting for `prune`. We need to check that all
+     * elements of `to_keep` are contained within the tree, and otherwise return false. However,
+     * if we did that at every step, then there will usually be some subtree which we check that
+     * does not contain all values of `to_keep`. Therefore, we do this check only once when we
+     * first call `prune`, and then we transfer control to `_prune`.
+     */
+    fn _prune(&mut self, to_keep: &[T]) -> bool {
+
+        let mut result = true;
+
+
+        let mut prune_left = true;
+        for element in to_keep {
+            if *element <= self.l_bound { prune_left = false; }
+        }
+
+        result = result && MerkleTree::prune_recurse(to_keep, &mut self.left, prune_left);
+
+        let mut prune_right = true; {           // We start a new scope here since self.find_min_right()
+                                                // borrows self.right
+
+            // We use the reference to one of the leaves of the tree to compute whether or not all
+            // the elements of to_keep are less than the min_right value, but after that we stop
+            // borrowing immutably so we can mutably borrow when mutating the right branch.
+            let min_right = match self.find_min_right() {
+                Ok(x) => x,
+                _ => { return result; }
+            };
+
+            for element in to_keep {
+                if *element >= *min_right {
+                    prune_right = false;
+                }
+            }
+        }
+
+        result && MerkleTree::prune_recurse(to_keep, &mut self.right, prune_right)
+    }
+
+
+    /**
+     * Helper function that handles recursion for `prune`. If we want to prune the branch,
+     * it handles computing the `Partial` branch. Otherwise, it attempts to recurse on that
+     * `Branch`, and otherwise stops recursion.
+     *
+     * # Errors
+     * Will error given a situation where `prune` attempts to prune an empty branch. This is impossible,
+     * since we cannot create a `Partial` branch if we have no data. However, this should never
+     * happen in practice, due to the checks `prune` runs before pruning a tree.
+     */
+    fn prune_recurse(to_keep: &[T], branch: &mut MerkleBranch<T>, should_prune: bool) -> bool {
+
+        let compute_branch = |br: &mut MerkleBranch<T>| {
+            match br {
+                Branch(node) =>  { Ok(Partial(node.mrkl_root.as_str().to_owned())) }
+                Leaf(hash_pointer) => { Ok(Partial(hash_pointer.hash.clone())) }
+                Partial(hash) => { Ok(Partial(hash.clone())) }
+                _ => Err(String::from("Cannot prune empty branch"))
+            }
+        };
+        // This error value should be unreachable. It would require us to have searched down the branch
+        // to find a value to compare to to_keep, and then come to find out that the branch is empty.
+
+        if should_prune {
+            *branch = compute_branch(branch).unwrap(); true
+        } else {
+            match branch {
+                Branch(node) => { node._prune(to_keep) }
+                _ => { true } // There is no more recursion to do. We either have that one of to_keep is
+            }                 // equal to the leaf value, or that it is an empty branch and so we shan't recurse.
+        }
+    }
+
+    /**
+     * Helper function for `prove` and `prove_leaf`. Copies the path chosen by `step` from this node down to a leaf,
+     * replacing every branch next to the path with a `Partial` holding its hash.
+     *
+     * # Return Value
+     * Returns `None` if `step` returns `None`, if the path runs into a pruned or empty branch, or if
+     * `accept` rejects the leaf at the end of the path.
+     */
+    fn copy_path(&self, step: &mut dyn FnMut(&MerkleTree<T>) -> Option<Side>, accept: &dyn Fn(&T) -> bool)
+            -> Option<MerkleTree<T>> {
+
+        let side = step(self)?;
+
+        let on_path = match self.child(side) {
+            Branch(node) => Branch(Box::new(node.copy_path(step, accept)?)),
+            Leaf(hpointer) if accept(&hpointer.ptr) => Leaf(hpointer.clone()),
+            _ => { return None; }
+        };
+
+        let off_path = match self.child(if side == Side::Left { Side::Right } else { Side::Left }) {
+            Branch(node) => Partial(node.mrkl_root.as_str().to_owned()),
+            Leaf(hpointer) => Partial(hpointer.hash.clone()),
+            Partial(hash) => Partial(hash.clone()),
+            Empty => Empty
+        };
+
+        let (left, right) = if side == Side::Left { (on_path, off_path) } else { (off_path, on_path) };
+
+        Some(MerkleTree {
+            left,
+            right,
+            l_bound: self.l_bound.clone(),
+            r_bound: self.r_bound.clone(),
+            mrkl_root: self.mrkl_root.clone(),
+            height: self.height,
+            pair_hashing: self.pair_hashing
+        })
+    }
+
+    /**
+     * Helper function for `replace_by`, which has already checked that the leaf exists. Replaces the leaf,
+     * then updates this node's bounds and `mrkl_root` on the way back up.
+     */
+    fn replace_on_path<F>(&mut self, f: &F, item: T) -> Option<T> where F: Fn(&T) -> Ordering {
+
+        let side = if f(&self.l_bound) != Ordering::Less { Side::Left } else { Side::Right };
+
+        if f(&self.l_bound) == Ordering::Equal { self.l_bound = item.clone(); }
+        if f(&self.r_bound) == Ordering::Equal { self.r_bound = item.clone(); }
+
+        let pair_hashing = self.pair_hashing;
+        let previous = match self.child_mut(side) {
+            Branch(node) => node.replace_on_path(f, item)?,
+            Leaf(hpointer) => *::std::mem::replace(hpointer, HashPointer::to_leaf(item, pair_hashing)).ptr,
+            _ => { return None; }
+        };
+
+        if let Some(left_hash) = self.left.hash() {
+            self.mrkl_root = self.pair_hashing.combine(left_hash, self.right.hash());
+        }
+
+        Some(previous)
+    }
+
+    /**
+     * Finds the leftmost leaf in the right child of the given Merkle tree. This will
+     * be the minimum value to the right of the current Merkle root if the tree is sorted.
+     *
+     * #Errors
+     * Will return an error if the right branch is partial or empty.
+     */
+    fn find_min_right(&self) -> Result<&T, String> {
+        match &self.right {
+            Branch(node) => node.find_min(),
+            Leaf(value) => Ok(value.ptr.as_ref()),
+            _ => Err(String::from("There is nowhere to search to the right to find the minimum element"))
+        }
+    }
+
+    /**
+     * Finds the leftmost leaf value in a given Merkle Tree. This will be the
+     * minimum value if the tree is sorted.
+     *
+     * # Errors
+     * Will return an error if the left branch is partial or empty.
+     */
+    fn find_min(&self) -> Result<&T, String> {
+        let mut node = self;
+        loop {
+            match &node.left {
+                Branch(left) => node = left,
+                Leaf(value) => { return Ok(value.ptr.as_ref()); }
+                _ => { return Err(String::from("Couldn't go left anymore when finding minimum element")); }
+            }
+        }
+    }
+
+    /*
+    --------------------------------------------------------------------------------------------------------
+    |                                    Private comparison methods                                        |
+    --------------------------------------------------------------------------------------------------------
+    */
+
+    /**
+     * Helper function for `first_divergent_leaf`. Compares two nodes of the same height, where the
+     * first leaf below them has index `offset`. Whenever a node has a right child, its left child is
+     * complete and so holds exactly `2^height` leaves.
+     */
+    fn divergence_from(&self, other: &MerkleTree<T>, offset: usize) -> Option<usize> {
+
+        if self.mrkl_root == other.mrkl_root {
+            return None;
+        }
+
+        match MerkleTree::branch_divergence(&self.left, &other.left, offset) {
+            Some(index) => Some(index),
+            None => MerkleTree::branch_divergence(&self.right, &other.right, offset + (1 << self.height))
+        }
+    }
+
+    /**
+     * Helper function for `first_divergent_leaf` which compares two children at the same position.
+     */
+    fn branch_divergence(left: &MerkleBranch<T>, right: &MerkleBranch<T>, offset: usize) -> Option<usize> {
+        match (left, right) {
+            (Branch(a), Branch(b)) => a.divergence_from(b, offset),
+            (a, b) => if a.hash() == b.hash() { None } else { Some(offset) }
+        }
+    }
+
+    /**
+     * Helper function for `extract_subtree`. If `branch` is the node we are looking for, replaces it
+     * with a partial branch and returns it. Otherwise keeps searching below `branch`.
+     */
+    fn extract_from(branch: &mut MerkleBranch<T>, root_hash: &MerkleRoot) -> Option<MerkleTree<T>> {
+
+        match branch {
+            Branch(ref node) if node.mrkl_root == *root_hash => {}
+            Branch(node) => { return node.extract_subtree(root_hash); }
+            _ => { return None; }
+        }
+
+        match ::std::mem::replace(branch, Partial(root_hash.as_str().to_owned())) {
+            Branch(node) => Some(*node),
+            _ => None
+        }
+    }
+
+    /**
+     * Helper function for `graft` and `graft_leaf`. Binary searches for `key`, and replaces the
+     * first partial branch it finds with `branch` if that branch's hash is `hash`. `height` is the
+     * height of the grafted node, or `None` if we are grafting a leaf.
+     */
+    fn graft_branch(&mut self, key: &T, hash: &str, height: Option<usize>, branch: MerkleBranch<T>)
+            -> Result<(), GraftError> {
+
+        let expected_height = match height {
+            Some(h) => h + 1,
+            None => 0
+        };
+        let mut node = self;
+
+        loop {
+            let own_height = node.height;
+            let slot = if *key <= node.l_bound { &mut node.left } else { &mut node.right };
+
+            let matches = match slot {
+                Branch(child) => { node = child; continue; }
+                Partial(pruned_hash) => pruned_hash == hash,
+                _ => { return Err(GraftError::NoPrunedBranch); }
+            };
+
+            return if !matches {
+                Err(GraftError::HashMismatch)
+            } else if own_height != expected_height {
+                Err(GraftError::HeightMismatch)
+            } else {
+                *slot = branch;
+                Ok(())
+            };
+        }
+    }
+
+    /**
+     * Helper function for `repair`. Returns the leaf at `path` as it should be restored from `originals`,
+     * or `None` if there is no leaf at `path` or it cannot be restored.
+     */
+    fn repaired_leaf(&self, path: &[Side], originals: &HashMap<String, &T>) -> Option<HashPointer<T>> {
+
+        let (&side, parent) = path.split_last()?;
+
+        match self.node_at(parent)?.child(side) {
+            Leaf(hpointer) => {
+                let item_hash = hpointer.ptr.leaf_hash(self.pair_hashing).into_string();
+                if originals.get(&item_hash).is_some_and(|original| **original == *hpointer.ptr) {
+                    Some(HashPointer { hash: item_hash, ptr: hpointer.ptr.clone() })
+                } else {
+                    originals.get(&hpointer.hash).map(|original| HashPointer::to_leaf((*original).clone(), self.pair_hashing))
+                }
+            }
+            _ => None
+        }
+    }
+
+    /**
+     * Helper function for `repair`. Recomputes the height and `mrkl_root` of the node at `path`, using the
+     * repaired `leaves` and the already recomputed `updates` of its children in place of what is stored.
+     */
+    fn recompute_node(&self, path: &[Side], leaves: &[(&[Side], HashPointer<T>)], updates: &[(&[Side], usize, MerkleRoot)])
+            -> (usize, MerkleRoot) {
+
+        let node = self.node_at(path).expect("Every repaired path leads to a node");
+        let mut height = node.height;
+        let mut hashes = Vec::new();
+
+        for &side in &[Side::Left, Side::Right] {
+            let mut child_path = path.to_vec();
+            child_path.push(side);
+
+            let leaf = leaves.iter().find(|&&(leaf_path, _)| leaf_path == &child_path[..]);
+            let update = updates.iter().find(|&&(node_path, _, _)| node_path == &child_path[..]);
+
+            hashes.push(match (node.child(side), leaf, update) {
+                (Leaf(_), Some((_, leaf)), _) => { height = 0; Some(leaf.hash.as_str()) }
+                (Leaf(hpointer), None, _) => { height = 0; Some(hpointer.hash.as_str()) }
+                (Branch(_), _, Some((_, child_height, root))) => { height = child_height + 1; Some(root.as_str()) }
+                (Branch(child), _, None) => { height = child.height + 1; Some(child.mrkl_root.as_str()) }
+                (branch, _, _) => branch.hash()
+            });
+        }
+
+        let root = match hashes[0] {
+            Some(left_hash) => self.pair_hashing.combine(left_hash, hashes[1]),
+            None => node.mrkl_root.clone()
+        };
+
+        (height, root)
+    }
+
+    fn node_at(&self, path: &[Side]) -> Option<&MerkleTree<T>> {
+        path.iter().try_fold(self, |node, &side| match node.child(side) {
+            Branch(child) => Some(&**child),
+            _ => None
+        })
+    }
+
+    fn node_at_mut(&mut self, path: &[Side]) -> Option<&mut MerkleTree<T>> {
+        let mut node = self;
+        for &side in path {
+            node = match node.child_mut(side) {
+                Branch(child) => child,
+                _ => { return None; }
+            };
+        }
+        Some(node)
+    }
+
+    fn child(&self, side: Side) -> &MerkleBranch<T> {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right
+        }
+    }
+
+    fn child_mut(&mut self, side: Side) -> &mut MerkleBranch<T> {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right
+        }
+    }
+
+    /**
+     * Builds a degenerate tree holding `item` in a single leaf, `depth` levels below the root. Each node
+     * above the leaf has it in its left child, and `sibling` as a pruned right child if given. Construction
+     * always balances its trees, so tests use this to check that deep trees do not overflow the stack.
+     */
+    #[cfg(test)]
+    pub(crate) fn degenerate(item: T, depth: usize, sibling: Option<&str>, pair_hashing: PairHashing) -> MerkleTree<T> {
+
+        let leaf = HashPointer::to_leaf(item.clone(), pair_hashing);
+        let mut node = MerkleTree {
+            mrkl_root: pair_hashing.combine(&leaf.hash, None),
+            left: Leaf(leaf),
+            right: Empty,
+            l_bound: item.clone(),
+            r_bound: item.clone(),
+            height: 0,
+            pair_hashing
+        };
+
+        for _ in 0..depth {
+            let right = sibling.map_or(Empty, |hash| Partial(String::from(hash)));
+            node = MerkleTree {
+                mrkl_root: pair_hashing.combine(node.mrkl_root.as_str(), right.hash()),
+                height: node.height + 1,
+                left: Branch(Box::new(node)),
+                right,
+                l_bound: item.clone(),
+                r_bound: item.clone(),
+                pair_hashing
+            };
+        }
+
+        node
     }
 
-     /**
-     * Validates a given pruned instance of `MerkleTree`.
-     *
-     * # Return Value
-     * Returns a `MrklVR` enumeration. See the documentation for `MrklVR` for the meanings
-     * of each result.
-     *
-     * # Panics
-     * In non-release builds panics if, when validating a fringe node, it encounters a situation
-     * where a right item hash is given but no right item is given, or vice versa. Note that in
-     * release builds this will cause `validate` to return `MrklVR::InvalidHash`.
+    /**
+     * Overwrites the stored hash of the leaf at `path`, or the `mrkl_root` of the node at `path`,
+     * so tests can corrupt a tree the way bit rot would.
      */
-    pub fn validate_pruned(&self) -> MrklVR {
-        self._validate(true)
+    #[cfg(test)]
+    pub(crate) fn corrupt(&mut self, path: &[Side], hash: &str) {
+        if let Some(node) = self.node_at_mut(path) {
+            node.mrkl_root = MerkleRoot::from_hex(String::from(hash));
+        } else if let Some((&side, parent)) = path.split_last() {
+            if let Some(Leaf(hpointer)) = self.node_at_mut(parent).map(|node| node.child_mut(side)) {
+                hpointer.hash = String::from(hash);
+            }
+        }
     }
 
     /*
     --------------------------------------------------------------------------------------------------------
-    |                                      Private pruning methods                                         |
+    |                                   Private validation methods                                         |
     --------------------------------------------------------------------------------------------------------
     */
 
     /**
-     * Helper function that does the heavy lifting for `prune`. We need to check that all
-     * elements of `to_keep` are contained within the tree, and otherwise return false. However,
-     * if we did that at every step, then there will usually be some subtree which we check that
-     * does not contain all values of `to_keep`. Therefore, we do this check only once when we
-     * first call `prune`, and then we transfer control to `_prune`.
+     * Builds a failed validation result for this node.
      */
-    fn _prune(&mut self, to_keep: &[T]) -> bool {
+    fn failure(&self, code: ErrorCode, message: &str) -> MrklVR {
+        MrklVR::failure(code, self.height, message)
+    }
 
-        let mut result = true;
+    /**
+     * Builds the failed validation result for a leaf of this fringe node whose item no longer matches its hash.
+     */
+    fn leaf_failure(&self, hpointer: &HashPointer<T>) -> MrklVR {
+        MrklVR::failure(ErrorCode::LeafHashMismatch, self.height, "A leaf's hash failed a hash check")
+            .with_hashes(hpointer.ptr.leaf_hash(self.pair_hashing).as_str(), &hpointer.hash)
+    }
 
+    /**
+     * Helper function for `validate_full` which checks every node of the tree in pre-order. The nodes
+     * are visited with an explicit stack rather than by recursing, so the depth of the tree does not
+     * matter.
+     */
+    fn collect_problems(&self, pruned: bool) -> Vec<ValidationError> {
 
-        let mut prune_left = true;
-        for element in to_keep {
-            if *element <= self.l_bound { prune_left = false; }
-        }
+        let mut problems = Vec::new();
+        let mut stack = vec!((self, Vec::new()));
 
-        result = result && MerkleTree::prune_recurse(to_keep, &mut self.left, prune_left);
+        while let Some((node, path)) = stack.pop() {
 
-        let mut prune_right = true; {           // We start a new scope here since self.find_min_right()
-                                                // borrows self.right
-            let min_right;                      // We use the reference to one of the leaves of
-            match self.find_min_right() {       // the tree to compute whether or not all the elements
-                Ok(x) => { min_right = x; }     // of to_keep are less than the min_right value,
-                _ => { return result; }         // but after that we stop borrowing immutably so we can
-            }                                   // mutably borrow when mutating the right branch.
+            node.node_problems(pruned, &path, &mut problems);
 
-            for element in to_keep {
-                if *element >= *min_right {
-                    prune_right = false;
+            for &(side, branch) in &[(Side::Right, &node.right), (Side::Left, &node.left)] {
+                if let Branch(child) = branch {
+                    let mut child_path = path.clone();
+                    child_path.push(side);
+                    stack.push((child, child_path));
                 }
             }
         }
 
-        result && MerkleTree::prune_recurse(to_keep, &mut self.right, prune_right)
+        problems
     }
 
-
     /**
-     * Helper function that handles recursion for `prune`. If we want to prune the branch,
-     * it handles computing the `Partial` branch. Otherwise, it attempts to recurse on that
-     * `Branch`, and otherwise stops recursion.
-     *
-     * # Errors
-     * Will error given a situation where `prune` attempts to prune an empty branch. This is impossible,
-     * since we cannot create a `Partial` branch if we have no data. However, this should never
-     * happen in practice, due to the checks `prune` runs before pruning a tree.
+     * Helper function for `validate_full` which checks this node on its own, without assuming its
+     * children are valid. Every problem found is pushed onto `problems`, with `path` as the path from
+     * the root of the validated tree to this node.
      */
-    fn prune_recurse(to_keep: &[T], branch: &mut MerkleBranch<T>, should_prune: bool) -> bool {
+    fn node_problems(&self, pruned: bool, path: &[Side], problems: &mut Vec<ValidationError>) {
 
-        let compute_branch = |br: &mut MerkleBranch<T>| {
-            match br {
-                Branch(node) =>  { Ok(Partial(node.mrkl_root.clone())) }
-                Leaf(hash_pointer) => { Ok(Partial(hash_pointer.hash.clone())) }
-                Partial(hash) => { Ok(Partial(hash.clone())) }
-                _ => Err(String::from("Cannot prune empty branch"))
+        let mut local = Vec::new();
+
+        match (&self.left, &self.right) {
+            (Branch(_), Branch(_)) | (Branch(_), Empty) | (Leaf(_), Leaf(_)) | (Leaf(_), Empty) => {}
+            (Partial(_), Partial(_))
+                => local.push(self.failure(ErrorCode::DoublePruned, "Invalid pruned tree. Only one child may be pruned.")),
+            (Partial(_), Empty)
+                => local.push(self.failure(ErrorCode::PrunedOnlyChild, "Invalid pruned tree. A node has one empty and one partial child.")),
+            (Partial(_), Branch(_)) | (Partial(_), Leaf(_)) | (Branch(_), Partial(_)) | (Leaf(_), Partial(_)) => {
+                if !pruned {
+                    local.push(self.failure(ErrorCode::UnexpectedPartial, "Unexpected pruned tree."));
+                }
             }
-        };
-        // This error value should be unreachable. It would require us to have searched down the branch
-        // to find a value to compare to to_keep, and then come to find out that the branch is empty.
+            _ => local.push(self.failure(ErrorCode::Malformed, "Malformed tree"))
+        }
 
-        if should_prune {
-            *branch = compute_branch(branch).unwrap(); true
-        } else {
+        let mut has_leaves = false;
+        for &(side, branch) in &[(Side::Left, &self.left), (Side::Right, &self.right)] {
             match branch {
-                Branch(node) => { node._prune(to_keep) }
-                _ => { true } // There is no more recursion to do. We either have that one of to_keep is
-            }                 // equal to the leaf value, or that it is an empty branch and so we shan't recurse.
+                Branch(node) if node.height + 1 != self.height => {
+                    local.push(self.failure(
+                        ErrorCode::HeightMismatch,
+                        "An internal node has height which differs from 1 + (child height)"
+                    ));
+                }
+                Leaf(hpointer) => {
+                    has_leaves = true;
+                    if !hpointer.verify_leaf_hash(self.pair_hashing) {
+                        local.push(self.leaf_failure(hpointer).within(side));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if has_leaves && self.height != 0 {
+            local.push(self.failure(ErrorCode::HeightMismatch, "A fringe node has nonzero height"));
+        }
+
+        if let Some(left_hash) = self.left.hash() {
+            let hash = self.pair_hashing.combine(left_hash, self.right.hash());
+            if hash != self.mrkl_root {
+                local.push(
+                    self.failure(ErrorCode::RootMismatch, "A node has an unexpected mrkl_root")
+                        .with_hashes(hash.as_str(), self.mrkl_root.as_str())
+                );
+            }
+        }
+
+        local.push(self.validate_order());
+
+        for result in local {
+            if let Err(mut error) = Result::<(), ValidationError>::from(result) {
+                let mut full_path = path.to_vec();
+                full_path.append(&mut error.path);
+                error.path = full_path;
+                problems.push(error);
+            }
         }
     }
 
     /**
-     * Finds the leftmost leaf in the right child of the given Merkle tree. This will
-     * be the minimum value to the right of the current Merkle root if the tree is sorted.
+     * Function which drives the validation of a Merkle tree. If pruned is false, then
+     * it will call any tree invalid with pruned hashes.
      *
-     * #Errors
-     * Will return an error if the right branch is partial or empty.
+     * Every node is checked after its children, and the left child before the right, so the first
+     * failure found is returned. The nodes are visited with an explicit stack rather than by
+     * recursing, so the depth of the tree does not matter.
      */
-    fn find_min_right(&self) -> Result<&T, String> {
-        match &self.right {
-            Branch(node) => node.find_min(),
-            Leaf(value) => Ok(value.ptr.as_ref()),
-            _ => Err(String::from("There is nowhere to search to the right to find the minimum element"))
+    fn _validate(&self, pruned: bool) -> MrklVR {
+
+        // Each entry holds a node, the length of its path, the side it hangs off its parent,
+        // and whether its children have already been checked. `path` always leads to the
+        // node popped last, since the nodes are popped in depth-first order.
+        let mut stack = vec!((self, 0, None, false));
+        let mut path = Vec::new();
+
+        while let Some((node, depth, side, children_checked)) = stack.pop() {
+
+            path.truncate(depth - side.map_or(0, |_| 1));
+            path.extend(side);
+
+            if !children_checked {
+                stack.push((node, depth, side, true));
+                for &(child_side, child) in node.children_to_validate(pruned).iter().rev() {
+                    stack.push((child, depth + 1, Some(child_side), false));
+                }
+                continue;
+            }
+
+            let result = match node.validate_structure(pruned) {
+                Valid => node.validate_order(),
+                result => result
+            };
+            if !result.is_valid() {
+                return path.iter().rev().fold(result, |result, &side| result.within(side));
+            }
         }
+
+        Valid
     }
 
     /**
-     * Finds the leftmost leaf value in a given Merkle Tree. This will be the
-     * minimum value if the tree is sorted.
-     *
-     * # Errors
-     * Will return an error if the left branch is partial or empty.
+     * Helper function for `_validate`. Returns the children which have to be validated before this node:
+     * its branches, unless this node's shape is already invalid.
      */
-    fn find_min(&self) -> Result<&T, String> {
-        match &self.left {
-            Branch(node) => node.find_min(),
-            Leaf(value) => Ok(value.ptr.as_ref()),
-            _ => Err(String::from("Couldn't go left anymore when finding minimum element"))
+    fn children_to_validate(&self, pruned: bool) -> Vec<(Side, &MerkleTree<T>)> {
+        match (&self.left, &self.right) {
+            (Branch(left), Branch(right)) => vec!((Side::Left, &**left), (Side::Right, &**right)),
+            (Branch(left), Empty) => vec!((Side::Left, &**left)),
+            (Branch(left), Partial(_)) if pruned => vec!((Side::Left, &**left)),
+            (Partial(_), Branch(right)) if pruned => vec!((Side::Right, &**right)),
+            _ => Vec::new()
         }
     }
 
-    /*
-    --------------------------------------------------------------------------------------------------------
-    |                                   Private validation methods                                         |
-    --------------------------------------------------------------------------------------------------------
-    */
-
     /**
-     * Function which drives the validation of a Merkle tree. If pruned is false, then
-     * it will call any tree invalid with pruned hashes.
+     * Validates the hashes and heights of this node, whose children have already been validated.
      */
-    fn _validate(&self, pruned: bool) -> MrklVR {
-
-        //##################################################################
-        //TODO: make sure leaves are in order.
+    fn validate_structure(&self, pruned: bool) -> MrklVR {
 
         match (&self.left, &self.right) {
 
            /*
-           * If there are two branches, then we recursively validate each branch.
-           * If they are both valid, then we return the result of self.validate_internal_node.
-           * Otherwise, we propagate whichever Invalid result was returned by calling validate
-           * on each branch.
+           * If there are two branches, both of which are valid, then we return the result
+           * of self.validate_internal_node.
            */
-           (Branch(ref left_br), Branch(ref right_br)) => {
-
-                match (left_br._validate(pruned), right_br._validate(pruned)) {
-
-                    (Valid, Valid) => self.validate_internal_node(&left_br, Some(&right_br)),
-
-                    (result@InvalidHash(_), _) | (_, result@InvalidHash(_)) => result,
-
-                    (result@_,_) => result,
-                }
-            }
+           (Branch(ref left_br), Branch(ref right_br))
+                    => self.validate_internal_node(left_br, Some(right_br)),
 
             /*
-            * If the right branch is empty and the left is a branch, then we validate the
-            * left branch only. We call self.validate_internal_node with Option::None as the right
-            * branch if the left branch passes the validation.
+            * If the right branch is empty and the left is a branch, then we call
+            * self.validate_internal_node with Option::None as the right branch.
             */
-            (Branch(ref branch), Empty) => {
-
-                match branch._validate(pruned) {
-                    Valid => self.validate_internal_node(branch, None),
-                    result@InvalidHash(_) | result@InvalidTree(_) => result
-                }
-
-            }
+            (Branch(ref branch), Empty)
+                    => self.validate_internal_node(branch, None),
 
             /*
             * If both children are leaves, then we can simply call self.validate_fringe_node.
-            * We no longer have to worry about recursively calling validate in this case since
-            * leaves just contain raw objects.
+            * Leaves just contain raw objects, so there are no children to validate first.
             */
             (Leaf(ref left_hpointer), Leaf(ref right_hpointer))
                     => self.validate_fringe_node(left_hpointer, Some(right_hpointer)),
@@ -498,15 +1889,23 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
             * We have no choice but to return an InvalidTree specification.
             */
             (Partial(_),Partial(_))
-                    => InvalidTree(String::from("Invalid pruned tree. Only one child may be pruned.")),
+                    => self.failure(ErrorCode::DoublePruned, "Invalid pruned tree. Only one child may be pruned."),
 
             /*
-            * Otherwise, if only one child is partial, then we can call self.evaluate_pruned_node.
+            * Otherwise, if only one child is partial, then we can call self.validate_pruned_node.
+            * We need to remember which side was pruned, since the order of the children matters
+            * when computing the `mrkl_root`.
             */
-            (Partial(hash), other@_) | (other@_, Partial(hash)) => {
-                if !pruned { InvalidTree(String::from("Unexpected pruned tree.")) }
+            (Partial(hash), other) => {
+                if !pruned { self.failure(ErrorCode::UnexpectedPartial, "Unexpected pruned tree.") }
                 else {
-                    self.validate_pruned_node(hash, other)
+                    self.validate_pruned_node(hash, other, true)
+                }
+            }
+            (other, Partial(hash)) => {
+                if !pruned { self.failure(ErrorCode::UnexpectedPartial, "Unexpected pruned tree.") }
+                else {
+                    self.validate_pruned_node(hash, other, false)
                 }
             }
 
@@ -515,11 +1914,51 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
             * error in the structure of the tree. Therefore, we always report that we have a malformed tree
             * if we get this far.
             */
-            (_,_) => InvalidTree(String::from("Malformed tree"))
+            (_,_) => self.failure(ErrorCode::Malformed, "Malformed tree")
         }
     }
 
 
+    /**
+     * Helper function for `MerkleTree::Validate` which checks that this node's leaves are sorted.
+     * Its children have already been validated, so each child's `r_bound` is known to be the largest
+     * element below that child. It therefore suffices to check that `l_bound` is the largest element
+     * of the left child, that `r_bound` is the largest element of the whole node, and that nothing in
+     * the left child is greater than the smallest element of the right child.
+     *
+     * Bounds which lie in a pruned branch cannot be checked, so they are skipped.
+     */
+    fn validate_order(&self) -> MrklVR {
+
+        let left_max = match self.left {
+            Branch(ref node) => Some(&node.r_bound),
+            Leaf(ref hpointer) => Some(hpointer.ptr.as_ref()),
+            _ => None
+        };
+        let right_max = match self.right {
+            Branch(ref node) => Some(&node.r_bound),
+            Leaf(ref hpointer) => Some(hpointer.ptr.as_ref()),
+            Empty => left_max,
+            Partial(_) => None
+        };
+
+        if left_max.is_some_and(|max| *max != self.l_bound) {
+            return self.failure(ErrorCode::BoundMismatch, "A node's l_bound is not the largest element of its left child");
+        }
+        if right_max.is_some_and(|max| *max != self.r_bound) {
+            return self.failure(ErrorCode::BoundMismatch, "A node's r_bound is not the largest element below it");
+        }
+
+        match (left_max, self.find_min_right()) {
+            (Some(max), Ok(min)) if max > min
+                => self.failure(
+                    ErrorCode::OutOfOrder,
+                    "The leaves of a node's left child are not all less than its right child"
+                ),
+            _ => Valid
+        }
+    }
+
     /**
      * Helper function for `MerkleTree::Validate` which validates an internal node in the Merkle tree.
      * It first computes the concatenated hash for its two children, and compares that with its
@@ -530,22 +1969,15 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
      */
     fn validate_internal_node(&self, left_node: &MerkleTree<T>, right_node: Option<&MerkleTree<T>>) -> MrklVR {
 
-        let mut hash = String::new();
-        hash.push_str(&left_node.mrkl_root);
-
         let mut right_has_correct_height = true;
-        match right_node {
-
-            Some(r) => {
-                hash.push_str(&r.mrkl_root);
-
-                right_has_correct_height = self.height == r.height + 1;
-            }
-
-            None => {}
+        if let Some(r) = right_node {
+            right_has_correct_height = self.height == r.height + 1;
         }
 
-        hash = hash.get_hash();
+        let hash = self.pair_hashing.combine(
+            left_node.mrkl_root.as_str(),
+            right_node.map(|r| r.mrkl_root.as_str())
+        );
 
         if hash == self.mrkl_root &&
            self.height == left_node.height + 1 &&
@@ -556,10 +1988,11 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
         else if self.height != left_node.height + 1 ||
                 !right_has_correct_height
         {
-            InvalidTree(String::from("An internal node has height which differs from 1 + (child height)"))
+            self.failure(ErrorCode::HeightMismatch, "An internal node has height which differs from 1 + (child height)")
         }
         else {
-            InvalidHash(String::from("An internal node has an unexpected mrkl_root"))
+            self.failure(ErrorCode::RootMismatch, "An internal node has an unexpected mrkl_root")
+                .with_hashes(hash.as_str(), self.mrkl_root.as_str())
         }
     }
 
@@ -571,80 +2004,85 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
     fn validate_fringe_node(&self, left_hpointer: &HashPointer<T>, right_hpointer: Option<&HashPointer<T>>)
             -> MrklVR {
 
-        let mut hash  = String::new();
-        hash.push_str( &left_hpointer.hash);
-
+        let left_hash_is_valid = left_hpointer.verify_leaf_hash(self.pair_hashing);
         let mut right_hash_is_valid = true;
-        match right_hpointer {
-
-            Some(r) => {
-                hash.push_str(&r.hash);
-
-                right_hash_is_valid = r.verify_hash();
-            }
-            None => {}
+        if let Some(r) = right_hpointer {
+            right_hash_is_valid = r.verify_leaf_hash(self.pair_hashing);
         }
 
-        hash = hash.get_hash();
+        let hash = self.pair_hashing.combine(
+            &left_hpointer.hash,
+            right_hpointer.map(|r| r.hash.as_str())
+        );
 
 
-        if  left_hpointer.verify_hash() &&
+        if  left_hash_is_valid &&
             right_hash_is_valid &&
             self.mrkl_root == hash &&
             self.height == 0 {
 
             Valid
         } else if self.mrkl_root != hash {
-            InvalidHash(String::from("A fringe node has an unexpected mrkl_root"))
+            self.failure(ErrorCode::RootMismatch, "A fringe node has an unexpected mrkl_root")
+                .with_hashes(hash.as_str(), self.mrkl_root.as_str())
         }
         else if self.height != 0 {
-            InvalidTree(String::from("A fringe node has nonzero height"))
+            self.failure(ErrorCode::HeightMismatch, "A fringe node has nonzero height")
         } else {
-            InvalidHash(String::from("A leaf's hash failed a hash check"))
+            let (side, hpointer) = match right_hpointer {
+                Some(r) if left_hash_is_valid => (Side::Right, r),
+                _ => (Side::Left, left_hpointer)
+            };
+            self.leaf_failure(hpointer).within(side)
         }
     }
 
     /**
      * Helper function for `MerkleTree::Validate` which validates a  node in the Merkle tree
      * which has a partial child. It enumerates the other child. If the other child is a branch,
-     * then the branches hash concatenated with the pruned hash must hash to this node's mrkl_root.
+     * which has already been validated, then the branches hash combined with the pruned hash must
+     * hash to this node's mrkl_root.
      * If the branch is a leaf, a similar check occurs, and we must further check that the leaf's
      * item hash still matches the computed item hash. In any other case we propagate Invalid errors.
+     *
+     * `pruned_on_left` specifies whether the partial child is this node's left child.
      */
-    fn validate_pruned_node(&self, pruned_hash: &str, other: &MerkleBranch<T>) -> MrklVR {
+    fn validate_pruned_node(&self, pruned_hash: &str, other: &MerkleBranch<T>, pruned_on_left: bool) -> MrklVR {
+
+        let combine_with_pruned = |other_hash: &str| {
+            if pruned_on_left {
+                self.pair_hashing.combine(pruned_hash, Some(other_hash))
+            } else {
+                self.pair_hashing.combine(other_hash, Some(pruned_hash))
+            }
+        };
+
+        let other_side = if pruned_on_left { Side::Right } else { Side::Left };
+
         match other {
             Branch(node) => {
-                match node.validate() {
-                    Valid => {
-                        let mut hash = String::new();
-                        hash.push_str(pruned_hash);
-                        hash.push_str(&node.mrkl_root);
-                        hash = hash.get_hash();
-                        if self.mrkl_root == hash {
-                            Valid
-                        } else {
-                            InvalidHash(String::from("An internal node had an unexpected mrkl_root"))
-                        }
-                    }
-                    result@_ => result
+                let hash = combine_with_pruned(node.mrkl_root.as_str());
+                if self.mrkl_root == hash {
+                    Valid
+                } else {
+                    self.failure(ErrorCode::RootMismatch, "An internal node had an unexpected mrkl_root")
+                        .with_hashes(hash.as_str(), self.mrkl_root.as_str())
                 }
             }
             Leaf(ref hpointer) => {
-                let mut hash = String::new();
-                hash.push_str(&hpointer.hash);
-                hash.push_str(pruned_hash);
-                hash = hash.get_hash();
-                if hpointer.verify_hash() && hash == self.mrkl_root {
+                let hash = combine_with_pruned(&hpointer.hash);
+                if hpointer.verify_leaf_hash(self.pair_hashing) && hash == self.mrkl_root {
                     Valid
-                } else if hpointer.verify_hash() {
-                    InvalidHash(String::from("A leaf's hash failed a hash check"))
+                } else if hpointer.verify_leaf_hash(self.pair_hashing) {
+                    self.failure(ErrorCode::RootMismatch, "A fringe node has an unexpected mrkl_root")
+                        .with_hashes(hash.as_str(), self.mrkl_root.as_str())
                 } else {
-                    InvalidHash(String::from("A fringe node has an unexpected mrkl_root"))
+                    self.leaf_failure(hpointer).within(other_side)
                 }
             }
-            Partial(_) => InvalidTree(String::from("Invalid pruned tree. Only one child may be pruned.")),
-            Empty => InvalidTree(String::from("Invalid pruned tree. Every node must
-                     have at least one valid child. This node has one empty and one partial child.")),
+            Partial(_) => self.failure(ErrorCode::DoublePruned, "Invalid pruned tree. Only one child may be pruned."),
+            Empty => self.failure(ErrorCode::PrunedOnlyChild, "Invalid pruned tree. Every node must \
+                     have at least one valid child. This node has one empty and one partial child."),
         }
     }
 
@@ -655,31 +2093,32 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
     */
 
     /**
-     * Helper function for `MerkleTree::construct`. Pops off the first element of
-     * `data` and creates a `MerkleBranch::Leaf`. It also pushes the hash of this first element
-     * into `hash`.
+     * Returns the number of nodes in a tree with `leaves` leaves, used to report construction progress.
      */
-    fn construct_leaf(data: &mut Vec<T>, hash: &mut String) -> MerkleBranch<T> {
-
-            let first = data.remove(0);
-            let first_hash = first.get_hash();
-
-            hash.push_str(&first_hash);
+    fn node_count(leaves: usize) -> usize {
+        let mut level = leaves.div_ceil(2);
+        let mut count = level;
+        while level > 1 {
+            level = level.div_ceil(2);
+            count += level;
+        }
+        count
+    }
 
-            Leaf(HashPointer::to(first))
+    /**
+     * Helper function for `MerkleTree::construct`. Pops off the first element of
+     * `data` and creates a `MerkleBranch::Leaf`, or returns `None` if `data` is empty.
+     */
+    fn construct_leaf(data: &mut VecDeque<T>, pair_hashing: PairHashing) -> Option<MerkleBranch<T>> {
+        data.pop_front().map(|first| Leaf(HashPointer::to_leaf(first, pair_hashing)))
     }
 
     /**
      * Helper function for `MerkleTree::construct`. Pops off the first element of `data`
-     * and creates a `MerkleBranch::Branch`. Also pushes the hash of this first element
-     * onto `hash`.
+     * and creates a `MerkleBranch::Branch`, or returns `None` if `data` is empty.
      */
-    fn construct_branch(data: &mut Vec<MerkleTree<T>>, hash: &mut String) -> MerkleBranch<T> {
-
-        let first = data.remove(0);
-        hash.push_str(&first.mrkl_root);
-
-        Branch(Box::new(first))
+    fn construct_branch(data: &mut VecDeque<MerkleTree<T>>) -> Option<MerkleBranch<T>> {
+        data.pop_front().map(|first| Branch(Box::new(first)))
     }
 
     /**
@@ -687,31 +2126,21 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
      * first two elements of `data`, where the children of this `MerkleTree` are
      * leaves.
      */
-    fn construct_fringe_node(data: &mut Vec<T>) -> Result<MerkleTree<T>, String> {
-
-        let mut hash = String::new();
-
-        let left_leaf = MerkleTree::construct_leaf(data, &mut hash);
+    fn construct_fringe_node(data: &mut VecDeque<T>, pair_hashing: PairHashing) -> Result<MerkleTree<T>, String> {
 
-        let mut right_leaf = Empty;
-        if data.len() > 0 {
+        let left_leaf = MerkleTree::construct_leaf(data, pair_hashing).unwrap_or(Empty);
+        let right_leaf = MerkleTree::construct_leaf(data, pair_hashing).unwrap_or(Empty);
 
-            right_leaf = MerkleTree::construct_leaf(data, &mut hash);
-
-        }
-        hash = hash.get_hash();
-
-        let l_bound;
-        match left_leaf {
-            Leaf(ref hpointer) => { l_bound = hpointer.ptr.as_ref().clone(); }
+        let l_bound = match left_leaf {
+            Leaf(ref hpointer) => hpointer.ptr.as_ref().clone(),
             _ => { return Err(String::from("Leaf contains no data")); }
-        }
+        };
+        let hash = pair_hashing.combine(left_leaf.hash().unwrap(), right_leaf.hash());
 
-        let mut r_bound = l_bound.clone();
-        match right_leaf {
-            Leaf(ref hpointer) => { r_bound = hpointer.ptr.as_ref().clone(); }
-            _ => {}
-        }
+        let r_bound = match right_leaf {
+            Leaf(ref hpointer) => hpointer.ptr.as_ref().clone(),
+            _ => l_bound.clone()
+        };
 
         Ok(MerkleTree{
             left: left_leaf,
@@ -719,7 +2148,8 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
             l_bound,
             r_bound,
             mrkl_root: hash,
-            height: 0
+            height: 0,
+            pair_hashing
         })
     }
 
@@ -727,30 +2157,22 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
      * Helper function for `MerkleTree::construct`. Creates a `MerkleTree` from the first
      * two elements of `data`, where the children of this `MerkleTree` are other `MerkleTree`s.
      */
-    fn construct_internal_node(data: &mut Vec<MerkleTree<T>>, height: usize) -> Result<MerkleTree<T>, String> {
-        let mut hash = String::new();
+    fn construct_internal_node(data: &mut VecDeque<MerkleTree<T>>, height: usize, pair_hashing: PairHashing)
+            -> Result<MerkleTree<T>, String> {
 
-        let left_branch = MerkleTree::construct_branch(data, &mut hash);
+        let left_branch = MerkleTree::construct_branch(data).unwrap_or(Empty);
+        let right_branch = MerkleTree::construct_branch(data).unwrap_or(Empty);
 
-        let mut right_branch = Empty;
-        if data.len() > 0 {
-            right_branch = MerkleTree::construct_branch(data, &mut hash);
-
-        }
-
-        hash = hash.get_hash();
-
-        let l_bound;
-        match left_branch {
-            Branch(ref node) => { l_bound = node.r_bound.clone(); }
+        let l_bound = match left_branch {
+            Branch(ref node) => node.r_bound.clone(),
             _ => { return Err(String::from("There was no r_bound to clone")); }
-        }
+        };
+        let hash = pair_hashing.combine(left_branch.hash().unwrap(), right_branch.hash());
 
-        let mut r_bound = l_bound.clone();
-        match right_branch {
-            Branch(ref node) => { r_bound = node.r_bound.clone(); }
-            _ => {}
-        }
+        let r_bound = match right_branch {
+            Branch(ref node) => node.r_bound.clone(),
+            _ => l_bound.clone()
+        };
 
         Ok(MerkleTree {
             left: left_branch,
@@ -758,7 +2180,110 @@ impl<T: Hashable + Ord + Clone> MerkleTree<T> {
             l_bound,
             r_bound,
             mrkl_root: hash,
-            height
+            height,
+            pair_hashing
         })
     }
-}
\ No newline at end of file
+}
+/**
+ * Recomputes the `mrkl_root` of a `MerkleTree` from its leaf hashes without building the tree.
+ *
+ * Leaf hashes must be pushed in the order the leaves appear in the tree, which for a
+ * `MerkleTree` is the sorted order of its items. The builder only keeps one pending hash per
+ * level, so it needs `O(log n)` memory no matter how many leaves are pushed.
+ *
+ * # Examples
+ *
+ * ```
+ * use newton::hash::LeafHash;
+ * use newton::merkle::{MerkleTree, PairHashing, RootBuilder};
+ *
+ * let data = vec!(String::from("a"), String::from("b"), String::from("c"));
+ * let mrkl_tree = MerkleTree::construct(data.clone()).unwrap();
+ *
+ * let mut builder = RootBuilder::new(PairHashing::Ordered);
+ * for item in &data {
+ *     builder.push(LeafHash::of(item).as_str());
+ * }
+ * assert_eq!(builder.finish().as_ref(), Some(mrkl_tree.root()));
+ * ```
+ */
+pub struct RootBuilder {
+    pair_hashing: PairHashing,
+    pending: Vec<Option<String>>
+}
+
+impl RootBuilder {
+
+    pub fn new(pair_hashing: PairHashing) -> Self {
+        RootBuilder { pair_hashing, pending: Vec::new() }
+    }
+
+    /**
+     * Adds the next leaf hash, as computed by `Hashable::leaf_hash`.
+     */
+    pub fn push(&mut self, leaf_hash: &str) {
+
+        let mut carry = String::from(leaf_hash);
+        let mut level = 0;
+
+        loop {
+            if level == self.pending.len() {
+                self.pending.push(None);
+            }
+
+            match self.pending[level].take() {
+                Some(left) => {
+                    carry = self.pair_hashing.combine(&left, Some(&carry)).into();
+                    level += 1;
+                }
+                None => {
+                    self.pending[level] = Some(carry);
+                    return;
+                }
+            }
+        }
+    }
+
+    /**
+     * Returns the `mrkl_root` of a `MerkleTree` with the pushed leaves, or `None` if no
+     * leaves were pushed.
+     */
+    pub fn finish(mut self) -> Option<MerkleRoot> {
+
+        let mut carry: Option<String> = None;
+
+        for level in 0..self.pending.len() {
+
+            let higher_exists = self.pending[level + 1..].iter().any(|node| node.is_some());
+
+            carry = match (self.pending[level].take(), carry) {
+                (Some(left), Some(right)) => Some(self.pair_hashing.combine(&left, Some(&right)).into()),
+
+                // A lone node is the root if nothing is left above it. Leaves always sit
+                // below at least one node, even when there is only one of them.
+                (Some(node), None) | (None, Some(node)) => {
+                    if level > 0 && !higher_exists {
+                        return Some(MerkleRoot::from_hex(node));
+                    }
+                    Some(self.pair_hashing.combine(&node, None).into())
+                }
+
+                (None, None) => None
+            };
+        }
+
+        carry.map(MerkleRoot::from_hex)
+    }
+
+    /**
+     * Checks that `leaf_hashes`, in tree order, hash up to `root`.
+     */
+    pub fn verify(root: &MerkleRoot, leaf_hashes: &[String], pair_hashing: PairHashing) -> bool {
+        let mut builder = RootBuilder::new(pair_hashing);
+        for hash in leaf_hashes {
+            builder.push(hash);
+        }
+        builder.finish().as_ref() == Some(root)
+    }
+}
@@ -0,0 +1,207 @@
+/*!
+ * A time-stamped notarization service. Currently supports:
+ * - Submitting document hashes
+ * - Sealing submitted hashes into batches, each committed to by a Merkle tree
+ * - Chaining the headers of batches together with hash pointers
+ * - Issuing `NotarizationReceipt`s which can be verified later against a batch header
+ *
+ * Sealing is driven by the caller, who decides how often batches are sealed and supplies their
+ * timestamps, so the notary never reads a clock itself.
+ *
+ * # Examples
+ *
+ * ```
+ * use newton::notary::Notary;
+ *
+ * let mut notary = Notary::new();
+ * notary.submit(String::from("3a7bd3e2360a3d29eea436fcfb7e44c735d117c42d1c1835420b6b9942dd4f1b"));
+ * let header_hash = notary.seal(1_500_000_000).unwrap().hash();
+ *
+ * let receipt = notary.receipt("3a7bd3e2360a3d29eea436fcfb7e44c735d117c42d1c1835420b6b9942dd4f1b").unwrap();
+ * assert!(receipt.verify(&header_hash));
+ * ```
+ */
+
+use hash::{BlockHash, Hashable, MerkleRoot};
+use merkle::MerkleTree;
+
+/**
+ * The header of a sealed batch of document hashes.
+ *
+ * # Fields
+ * `index`: The position of the batch in the chain, starting at 0.
+ *
+ * `timestamp`: The time the batch was sealed, as supplied to `Notary::seal`.
+ *
+ * `mrkl_root`: The root of the Merkle tree over the batch's document hashes.
+ *
+ * `previous`: The hash of the previous batch's header, or `None` for the first batch.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchHeader {
+    pub index: u64,
+    pub timestamp: u64,
+    pub mrkl_root: MerkleRoot,
+    pub previous: Option<BlockHash>
+}
+
+/**
+ * A proof that a document hash was notarized in a given batch. `proof` is the batch's Merkle tree
+ * pruned down to `document_hash`.
+ */
+#[derive(Clone)]
+pub struct NotarizationReceipt {
+    pub document_hash: String,
+    pub header: BatchHeader,
+    pub proof: MerkleTree<String>
+}
+
+/**
+ * Collects document hashes and seals them into a chain of batches.
+ */
+pub struct Notary {
+    pending: Vec<String>,
+    headers: Vec<BatchHeader>,
+    batches: Vec<MerkleTree<String>>
+}
+
+impl BatchHeader {
+
+    /**
+     * Returns the hash of this header, which the next header points to.
+     */
+    pub fn hash(&self) -> BlockHash {
+        BlockHash::from_hex(self.get_hash())
+    }
+}
+
+/**
+ * Computes sha2("newton/block:" || index || ":" || timestamp || ":" || mrkl_root || ":" || previous).
+ */
+impl Hashable for BatchHeader {
+    fn get_hash(&self) -> String {
+        let previous = self.previous.as_ref().map_or("", |hash| hash.as_str());
+        BlockHash::tagged(
+            &format!("{}:{}:{}:{}", self.index, self.timestamp, self.mrkl_root, previous)
+        ).into()
+    }
+}
+
+impl NotarizationReceipt {
+
+    /**
+     * Checks that this receipt's header has the hash `header_hash`, and that its proof shows
+     * `document_hash` under that header's `mrkl_root`. `header_hash` should come from a trusted
+     * copy of the notary's chain of headers.
+     */
+    pub fn verify(&self, header_hash: &BlockHash) -> bool {
+        self.header.hash() == *header_hash &&
+        *self.proof.root() == self.header.mrkl_root &&
+        self.proof.validate_pruned().is_valid() &&
+        self.proof.contains(&self.document_hash) == Ok(true)
+    }
+}
+
+impl Notary {
+
+    pub fn new() -> Self {
+        Notary { pending: Vec::new(), headers: Vec::new(), batches: Vec::new() }
+    }
+
+    /**
+     * Submits a document hash to be notarized in the next sealed batch.
+     */
+    pub fn submit(&mut self, document_hash: String) {
+        self.pending.push(document_hash);
+    }
+
+    /**
+     * Returns the number of document hashes waiting for the next batch.
+     */
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /**
+     * Seals every pending document hash into a new batch, and appends its header to the chain.
+     *
+     * # Errors
+     * Will return an error if there are no pending document hashes, or if `timestamp` is earlier
+     * than the timestamp of the previous batch.
+     */
+    pub fn seal(&mut self, timestamp: u64) -> Result<&BatchHeader, String> {
+
+        if self.pending.is_empty() {
+            return Err(String::from("There are no pending document hashes to seal"));
+        }
+        if self.headers.last().is_some_and(|header| header.timestamp > timestamp) {
+            return Err(String::from("A batch cannot be sealed before the previous batch"));
+        }
+
+        let tree = MerkleTree::construct(::std::mem::take(&mut self.pending))?;
+        let header = BatchHeader {
+            index: self.headers.len() as u64,
+            timestamp,
+            mrkl_root: tree.root().clone(),
+            previous: self.headers.last().map(BatchHeader::hash)
+        };
+
+        self.batches.push(tree);
+        self.headers.push(header);
+        Ok(&self.headers[self.headers.len() - 1])
+    }
+
+    /**
+     * Returns the headers of every sealed batch, oldest first.
+     */
+    pub fn headers(&self) -> &[BatchHeader] {
+        &self.headers
+    }
+
+    /**
+     * Issues a receipt for `document_hash` from the first batch it was sealed in.
+     *
+     * # Return Value
+     * Returns `None` if `document_hash` has not been sealed in any batch yet.
+     */
+    pub fn receipt(&self, document_hash: &str) -> Option<NotarizationReceipt> {
+
+        let document_hash = String::from(document_hash);
+
+        for (header, tree) in self.headers.iter().zip(&self.batches) {
+            if let Some(proof) = tree.prove(&document_hash) {
+                return Some(NotarizationReceipt { document_hash, header: header.clone(), proof });
+            }
+        }
+
+        None
+    }
+
+    /**
+     * Checks that `headers` form a chain: each header has the next index and a timestamp no
+     * earlier than the one before it, and points to the hash of the header before it.
+     */
+    pub fn verify_chain(headers: &[BatchHeader]) -> bool {
+
+        let mut previous: Option<&BatchHeader> = None;
+
+        for (index, header) in headers.iter().enumerate() {
+            let linked = match previous {
+                Some(prev) => header.previous == Some(prev.hash()) && header.timestamp >= prev.timestamp,
+                None => header.previous.is_none()
+            };
+            if !linked || header.index != index as u64 {
+                return false;
+            }
+            previous = Some(header);
+        }
+
+        true
+    }
+}
+
+impl Default for Notary {
+    fn default() -> Self {
+        Notary::new()
+    }
+}
@@ -0,0 +1,98 @@
+/*!
+ * Progress reporting and cancellation for long-running operations. Currently supports:
+ * - `MerkleTree::construct_with_progress`
+ *
+ * A `ProgressHandle` is shared between the code running an operation and the code watching it,
+ * for example a GUI thread. Cloning a handle is cheap, and every clone refers to the same
+ * operation, so one clone can be moved into the worker while another is used to cancel it.
+ *
+ * # Examples
+ *
+ * ```
+ * use newton::merkle::{MerkleTree, PairHashing};
+ * use newton::progress::ProgressHandle;
+ *
+ * let handle = ProgressHandle::with_callback(|percent| println!("{}% done", percent));
+ * let data: Vec<String> = (0..1000).map(|i| i.to_string()).collect();
+ *
+ * let tree = MerkleTree::construct_with_progress(data, PairHashing::Ordered, &handle);
+ * assert!(tree.is_ok());
+ * ```
+ */
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/**
+ * The error message returned by an operation which stopped because its `ProgressHandle` was
+ * cancelled. Callers can compare an error against it to tell cancellation apart from a failure.
+ */
+pub const CANCELLED: &str = "The operation was cancelled";
+
+/**
+ * A handle used to follow the progress of an operation and to cancel it.
+ *
+ * # Fields
+ * `cancelled`: Set by `cancel`. Operations check it regularly and stop once it is set.
+ *
+ * `reported`: The last percentage passed to `callback`, plus one, or 0 if none has been passed yet.
+ *
+ * `callback`: Called with the percentage of work done, from 0 to 100, each time it changes.
+ */
+#[derive(Clone, Default)]
+pub struct ProgressHandle {
+    cancelled: Arc<AtomicBool>,
+    reported: Arc<AtomicUsize>,
+    callback: Option<Arc<dyn Fn(u8) + Send + Sync>>
+}
+
+impl ProgressHandle {
+
+    /**
+     * Creates a handle which can cancel an operation but reports no progress.
+     */
+    pub fn new() -> Self {
+        ProgressHandle::default()
+    }
+
+    /**
+     * Creates a handle which calls `callback` with the percentage of work done each time it changes.
+     * The callback runs on the thread doing the work, so it should return quickly.
+     */
+    pub fn with_callback<F: Fn(u8) + Send + Sync + 'static>(callback: F) -> Self {
+        ProgressHandle { callback: Some(Arc::new(callback)), ..ProgressHandle::default() }
+    }
+
+    /**
+     * Asks the operation using this handle to stop. The operation returns `CANCELLED` as its error
+     * the next time it checks the handle.
+     */
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /**
+     * Returns whether `cancel` has been called on this handle or any of its clones. Once cancelled,
+     * a handle stays cancelled.
+     */
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /**
+     * Records that `done` out of `total` units of work are complete, calling the callback if the
+     * percentage changed.
+     */
+    pub(crate) fn report(&self, done: usize, total: usize) {
+
+        let callback = match self.callback {
+            Some(ref callback) => callback,
+            None => { return; }
+        };
+
+        let percent = ::std::cmp::min((done * 100).checked_div(total).unwrap_or(100), 100);
+        if self.reported.swap(percent + 1, Ordering::SeqCst) != percent + 1 {
+            callback(percent as u8);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+/*!
+ * A common interface for authenticated key-value state. Currently supports:
+ * - The `StateCommitment` trait
+ * - `map::MerkleMap` as a `StateCommitment` backend
+ *
+ * Code written against `StateCommitment` can switch between commitment schemes without changes.
+ *
+ * # Examples
+ *
+ * ```
+ * use newton::map::MerkleMap;
+ * use newton::state::StateCommitment;
+ *
+ * fn transfer<S: StateCommitment<String, String>>(state: &mut S) {
+ *     state.insert(String::from("alice"), String::from("5"));
+ * }
+ *
+ * let mut state = MerkleMap::construct(vec!((String::from("alice"), String::from("10")))).unwrap();
+ * transfer(&mut state);
+ *
+ * let proof = state.prove(&String::from("alice")).unwrap();
+ * let root = StateCommitment::root(&state);
+ * assert!(<MerkleMap<String, String> as StateCommitment<_, _>>::verify(
+ *     &root, &String::from("alice"), &String::from("5"), &proof
+ * ));
+ * ```
+ */
+
+use hash::{Hashable, MerkleRoot};
+use map::{MapEntry, MerkleMap};
+use merkle::MerkleTree;
+
+/**
+ * A key-value store which commits to its contents with a single root hash, and can prove what
+ * value a key maps to under that root.
+ */
+pub trait StateCommitment<K, V> {
+
+    /// A proof that a key maps to a value under a given root.
+    type Proof;
+
+    /**
+     * Stores `value` under `key`, changing the root. The cost depends on the backend: `MerkleMap`
+     * replaces an existing key's value in `O(log n)` time, but rebuilds its whole tree to add a new
+     * key, which takes `O(n log n)` time.
+     *
+     * # Return Value
+     * Returns the value previously stored under `key`, if any.
+     */
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+
+    /**
+     * Returns the value stored under `key`, or `None` if there is no such key.
+     */
+    fn get(&self, key: &K) -> Option<&V>;
+
+    /**
+     * Returns the root hash committing to every key and value.
+     */
+    fn root(&self) -> MerkleRoot;
+
+    /**
+     * Returns a proof that `key` maps to its current value, or `None` if there is no such key.
+     */
+    fn prove(&self, key: &K) -> Option<Self::Proof>;
+
+    /**
+     * Checks that `proof` shows `key` mapping to `value` under `root`.
+     */
+    fn verify(root: &MerkleRoot, key: &K, value: &V, proof: &Self::Proof) -> bool;
+}
+
+impl<K: Hashable + Ord + Clone, V: Hashable + Clone> StateCommitment<K, V> for MerkleMap<K, V> {
+
+    type Proof = MerkleTree<MapEntry<K, V>>;
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        MerkleMap::insert(self, key, value)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        MerkleMap::get(self, key)
+    }
+
+    fn root(&self) -> MerkleRoot {
+        MerkleMap::root(self).clone()
+    }
+
+    fn prove(&self, key: &K) -> Option<Self::Proof> {
+        self.get_with_proof(key).map(|(_, proof)| proof)
+    }
+
+    fn verify(root: &MerkleRoot, key: &K, value: &V, proof: &Self::Proof) -> bool {
+        MerkleMap::verify(root, key, value, proof)
+    }
+}
@@ -0,0 +1,258 @@
+/*!
+ * Merkle-sum trees, as used by exchanges to prove their reserves. Currently supports:
+ * - Building a tree over `(id, amount)` leaves, where each node carries a hash and a sum
+ * - Proofs that a leaf is included in a tree and counted towards its total
+ * - Blinded sums, added modulo 2^64 so that random offsets on the leaves can hide every sum but the total
+ *
+ * Each node's hash commits to its children's hashes and sums, and its sum is the sum of its
+ * children's sums. A `SumProof` therefore shows both that a leaf is in the tree and that its
+ * amount is included in the total published with the root. Since every sum is a `u64`, no node
+ * can hide a negative amount, and a tree whose total overflows cannot be built.
+ *
+ * Like a `BlobTree`, a `MerkleSumTree` keeps its leaves in the order they were given, and a node
+ * without a right sibling is hashed on its own.
+ *
+ * # Examples
+ *
+ * ```
+ * use newton::sum::MerkleSumTree;
+ *
+ * let tree = MerkleSumTree::construct(vec!(
+ *     (String::from("alice"), 50),
+ *     (String::from("bob"), 20),
+ *     (String::from("carol"), 30)
+ * )).unwrap();
+ * assert_eq!(tree.total(), 100);
+ *
+ * let proof = tree.prove(1).unwrap();
+ * assert!(proof.verify(tree.root(), "bob", 20));
+ * assert!(!proof.verify(tree.root(), "bob", 2));
+ * ```
+ */
+
+use hash::{Hashable, SumHash};
+
+/**
+ * A node of a `MerkleSumTree`: the hash committing to the node, and the sum of every leaf
+ * amount below it.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SumNode {
+    pub hash: SumHash,
+    pub sum: u64
+}
+
+/**
+ * Enumerates the ways in which a `MerkleSumTree` adds up the sums of a node's children.
+ *
+ * `Plain` adds them as `u64`s and fails on overflow, so no node can hide a negative amount. This is the default.
+ *
+ * `Blinded` adds them modulo 2^64. Each leaf can then carry its amount plus a random blinding offset, with
+ * offsets chosen to cancel out over the whole tree. The root still carries the true total, but every other
+ * sum is offset by the sum of the blinding offsets below it, which looks random to anyone who does not know
+ * them. The price is that a verifier can no longer tell a large sum from a negative one.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SumMode {
+    Plain,
+    Blinded
+}
+
+/**
+ * A Merkle tree whose nodes carry the sum of the amounts below them.
+ *
+ * # Fields
+ * `levels`: The nodes of every level of the tree. `levels[0]` holds the leaves in their original
+ * order, and the last level holds only the root.
+ *
+ * `mode`: How the sums of sibling nodes are added up.
+ */
+#[derive(Clone, Debug)]
+pub struct MerkleSumTree {
+    levels: Vec<Vec<SumNode>>,
+    mode: SumMode
+}
+
+/**
+ * A proof that a leaf is included in the `MerkleSumTree` with a given root.
+ *
+ * `siblings` contains the sibling at each level of the tree, starting at the leaves. A sibling
+ * is `None` when the node on the path has no right sibling.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SumProof {
+    pub index: usize,
+    pub siblings: Vec<Option<SumNode>>
+}
+
+impl SumNode {
+
+    /**
+     * Returns the leaf committing to `id` and `amount`, whose hash is
+     * sha2("newton/sum-node:leaf:" || sha2(id) || ":" || amount).
+     */
+    pub fn leaf(id: &str, amount: u64) -> Self {
+        SumNode {
+            hash: SumHash::tagged(&format!("leaf:{}:{}", String::from(id).get_hash(), amount)),
+            sum: amount
+        }
+    }
+
+    /**
+     * Returns the parent of `left` and `right`, whose hash commits to both children's hashes and
+     * sums. A node without a right sibling is hashed on its own.
+     *
+     * # Return Value
+     * Returns `None` if the sum of the children overflows.
+     */
+    pub fn parent(left: &SumNode, right: Option<&SumNode>) -> Option<Self> {
+        SumNode::parent_with(left, right, SumMode::Plain)
+    }
+
+    /**
+     * Returns the parent of `left` and `right` like `SumNode::parent`, adding their sums according to `mode`.
+     *
+     * # Return Value
+     * Returns `None` if `mode` is `Plain` and the sum of the children overflows.
+     */
+    pub fn parent_with(left: &SumNode, right: Option<&SumNode>, mode: SumMode) -> Option<Self> {
+        match right {
+            Some(right) => Some(SumNode {
+                hash: SumHash::tagged(&format!("{}:{}:{}:{}", left.hash, left.sum, right.hash, right.sum)),
+                sum: match mode {
+                    SumMode::Plain => left.sum.checked_add(right.sum)?,
+                    SumMode::Blinded => left.sum.wrapping_add(right.sum)
+                }
+            }),
+            None => Some(SumNode {
+                hash: SumHash::tagged(&format!("{}:{}", left.hash, left.sum)),
+                sum: left.sum
+            })
+        }
+    }
+}
+
+impl MerkleSumTree {
+
+    /**
+     * Builds a `MerkleSumTree` over `leaves`, each an `(id, amount)` pair, keeping them in order.
+     *
+     * # Errors
+     * Will return an error if `leaves` is empty, or if the total of every amount overflows a `u64`.
+     */
+    pub fn construct(leaves: Vec<(String, u64)>) -> Result<Self, String> {
+        MerkleSumTree::construct_with(leaves, SumMode::Plain)
+    }
+
+    /**
+     * Builds a `MerkleSumTree` like `MerkleSumTree::construct`, adding up sums according to `mode`.
+     * Under `Blinded`, the amounts of `leaves` should already include their blinding offsets.
+     *
+     * # Errors
+     * Will return an error if `leaves` is empty, or if `mode` is `Plain` and the total of every amount
+     * overflows a `u64`.
+     */
+    pub fn construct_with(leaves: Vec<(String, u64)>, mode: SumMode) -> Result<Self, String> {
+
+        trace_span!("sum::construct", leaves = leaves.len(), ?mode);
+
+        if leaves.is_empty() {
+            return Err(String::from(
+                "Not enough data to construct Merkle-sum tree. Must receive at least one leaf."
+            ));
+        }
+
+        let mut levels = vec!(leaves.iter().map(|&(ref id, amount)| SumNode::leaf(id, amount)).collect::<Vec<_>>());
+
+        while levels.len() == 1 || levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| SumNode::parent_with(&pair[0], pair.get(1), mode))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| String::from("The total of the leaf amounts overflows"))?;
+            levels.push(next);
+        }
+
+        Ok(MerkleSumTree { levels, mode })
+    }
+
+    /**
+     * Returns the root of the tree, whose hash and sum are what gets published.
+     */
+    pub fn root(&self) -> &SumNode {
+        &self.levels[self.levels.len() - 1][0]
+    }
+
+    /**
+     * Returns the sum of every leaf amount in the tree.
+     */
+    pub fn total(&self) -> u64 {
+        self.root().sum
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn mode(&self) -> SumMode {
+        self.mode
+    }
+
+    /**
+     * Generates a `SumProof` for the leaf at `index`, or `None` if there is no such leaf.
+     */
+    pub fn prove(&self, index: usize) -> Option<SumProof> {
+
+        if index >= self.leaf_count() { return None; }
+
+        let mut siblings = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            siblings.push(level.get(position ^ 1).cloned());
+            position /= 2;
+        }
+
+        Some(SumProof { index, siblings })
+    }
+}
+
+impl SumProof {
+
+    /**
+     * Checks that the leaf at `self.index` of the tree with root `root` commits to `id` and
+     * `amount`. Since `root` carries the tree's total, a valid proof also shows that `amount`
+     * is counted towards that total.
+     */
+    pub fn verify(&self, root: &SumNode, id: &str, amount: u64) -> bool {
+        self.verify_with(root, id, amount, SumMode::Plain)
+    }
+
+    /**
+     * Checks the proof like `SumProof::verify`, for a tree whose sums are added up according to `mode`.
+     * Under `Blinded`, `amount` is the leaf's amount plus its blinding offset.
+     */
+    pub fn verify_with(&self, root: &SumNode, id: &str, amount: u64, mode: SumMode) -> bool {
+        self.root_from(SumNode::leaf(id, amount), mode).is_some_and(|node| node == *root)
+    }
+
+    /*
+     * Recomputes the root from `leaf` and the siblings, or returns `None` if the proof is
+     * malformed or a sum overflows.
+     */
+    fn root_from(&self, leaf: SumNode, mode: SumMode) -> Option<SumNode> {
+
+        let mut node = leaf;
+        let mut position = self.index;
+
+        for sibling in &self.siblings {
+            node = match (position % 2, sibling) {
+                (0, sibling) => SumNode::parent_with(&node, sibling.as_ref(), mode)?,
+                (_, Some(sibling)) => SumNode::parent_with(sibling, Some(&node), mode)?,
+                (_, None) => { return None; }
+            };
+            position /= 2;
+        }
+
+        if position == 0 { Some(node) } else { None }
+    }
+}
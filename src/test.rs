@@ -1,13 +1,161 @@
+
 use super::*;
+use hash::Hashable;
 
 #[test]
+#[allow(clippy::print_with_newline)]
 fn hash_pointer() {
     let name = String::from("riley");
     let hash_ptr = hash::HashPointer::to(name);
     print!("Name:  {}, with hash of: {}\n", hash_ptr.ptr, hash_ptr.hash);
 }
 
+/*
+ * A Hashable item which streams its fields into the hasher instead of building a String.
+ */
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct Transfer {
+    from: String,
+    to: String,
+    amount: u64
+}
+
+impl Hashable for Transfer {
+    fn get_hash(&self) -> String {
+        hash::stream_hash(self)
+    }
+
+    // Each name is prefixed with its length, so no two transfers feed in the same bytes.
+    fn hash_into(&self, hasher: &mut dyn hash::Digest) {
+        for name in &[&self.from, &self.to] {
+            hasher.input(&(name.len() as u64).to_be_bytes());
+            name.hash_into(hasher);
+        }
+        hasher.input(&self.amount.to_be_bytes());
+    }
+}
+
+#[test]
+fn streaming_hash() {
+    let transfer = Transfer { from: String::from("alice"), to: String::from("bob"), amount: 5 };
+
+    let mut expected = 5u64.to_be_bytes().to_vec();
+    expected.extend_from_slice(b"alice");
+    expected.extend_from_slice(&3u64.to_be_bytes());
+    expected.extend_from_slice(b"bob");
+    expected.extend_from_slice(&5u64.to_be_bytes());
+    assert_eq!(transfer.get_hash(), expected.get_hash());
+
+    let shifted = Transfer { from: String::from("alic"), to: String::from("ebob"), ..transfer.clone() };
+    assert_ne!(transfer.get_hash(), shifted.get_hash());
+
+    assert_eq!(String::from("abc").get_hash(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+    let transfers: Vec<Transfer> = (0..5).map(|amount| Transfer { amount, ..transfer.clone() }).collect();
+    let m_tree = merkle::MerkleTree::construct(transfers.clone()).unwrap();
+    assert!(m_tree.validate().is_valid());
+    assert_eq!(m_tree.contains(&transfers[3]), Ok(true));
+    assert_eq!(hash::LeafHash::of(&transfer), hash::LeafHash::tagged_bytes(&expected));
+}
+
+/*
+ * A Hashable item which can only be streamed, to check that trees never call `get_hash` on their leaves.
+ */
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct StreamOnly(u32);
+
+impl Hashable for StreamOnly {
+    fn get_hash(&self) -> String {
+        panic!("Leaves should be hashed through hash_into")
+    }
+
+    fn hash_into(&self, hasher: &mut dyn hash::Digest) {
+        hasher.input(&self.0.to_be_bytes());
+    }
+}
+
 #[test]
+fn merkle_streams_leaves() {
+    use merkle::Side::{Left, Right};
+
+    let items: Vec<StreamOnly> = (0..9).map(StreamOnly).collect();
+    let mut m_tree = merkle::MerkleTree::construct(items.clone()).unwrap();
+    assert!(m_tree.validate_full().is_valid());
+
+    m_tree.corrupt(&[Left, Left, Left, Right], "rot");
+    let report = m_tree.validate_full();
+    assert!(!report.is_valid());
+    assert!(m_tree.repair(&report, &items).is_ok());
+    assert!(m_tree.validate().is_valid());
+
+    let proof = m_tree.prove(&items[4]).unwrap();
+    assert!(proof.validate_pruned().is_valid());
+    assert!(m_tree.graft_leaf(StreamOnly(4)).is_err());
+}
+
+#[test]
+fn typed_hashes() {
+    let data = String::from("riley");
+
+    let tx_id = hash::TxId::tagged(&data);
+    let block_hash = hash::BlockHash::tagged(&data);
+    assert_ne!(tx_id.as_str(), block_hash.as_str());
+    assert_ne!(tx_id.as_str(), data.get_hash());
+    assert_eq!(tx_id, hash::TxId::from_hex(String::from(tx_id.as_str())));
+
+    let leaf_hash = hash::LeafHash::of(&data);
+    assert_eq!(leaf_hash, hash::LeafHash::tagged(&data));
+    assert_ne!(leaf_hash.as_str(), data.get_hash());
+    assert_ne!(hash::ChunkHash::tagged(&data).as_str(), leaf_hash.as_str());
+    assert_eq!(leaf_hash.leaf_hash(merkle::PairHashing::Ordered), leaf_hash);
+    assert_eq!(leaf_hash.leaf_hash(merkle::PairHashing::OpenZeppelin), leaf_hash);
+
+    let hash_ptr = hash::HashPointer::to_leaf(data, merkle::PairHashing::Ordered);
+    assert!(hash_ptr.verify_leaf_hash(merkle::PairHashing::Ordered));
+    assert_eq!(hash_ptr.leaf_hash(), leaf_hash);
+}
+
+#[test]
+fn merkle_root() {
+    let left = String::from("left");
+    let right = String::from("right");
+
+    let mut concat = hash::LeafHash::of(&left).into_string();
+    concat.push_str(hash::LeafHash::of(&right).as_str());
+
+    let m_tree = merkle::MerkleTree::construct(vec!(right, left)).unwrap();
+    assert_eq!(*m_tree.root(), hash::MerkleRoot::tagged(&concat));
+}
+
+#[test]
+fn merkle_leaf_is_not_a_node() {
+    let items: Vec<String> = (0..4).map(|i: u32| i.to_string()).collect();
+    let m_tree = merkle::MerkleTree::construct_with(items.clone(), merkle::PairHashing::SortedPair).unwrap();
+
+    // The preimage of each fringe node, as it would be hashed under the node's tag.
+    let preimages: Vec<String> = items.chunks(2).map(|pair| {
+        let (a, b) = (hash::LeafHash::of(&pair[0]).into_string(), hash::LeafHash::of(&pair[1]).into_string());
+        if a < b { a + &b } else { b + &a }
+    }).collect();
+    let nodes: Vec<hash::MerkleRoot> = preimages.iter().map(|preimage| hash::MerkleRoot::tagged(preimage)).collect();
+    assert_eq!(*m_tree.root(), merkle::PairHashing::SortedPair.combine(nodes[0].as_str(), Some(nodes[1].as_str())));
+
+    // Leaves holding the tagged preimages would hash to the nodes themselves if leaves were untagged.
+    let forged_items: Vec<String> = preimages.iter().map(|preimage| format!("{}:{}", hash::MerkleRoot::TAG, preimage)).collect();
+    for (item, node) in forged_items.iter().zip(&nodes) {
+        assert_eq!(item.get_hash(), node.as_str());
+        assert_ne!(hash::LeafHash::of(item).as_str(), node.as_str());
+    }
+
+    let forged = merkle::MerkleTree::construct_with(forged_items.clone(), merkle::PairHashing::SortedPair).unwrap();
+    assert!(!forged.same_root(&m_tree));
+    let forged_leaves = merkle::MerkleTree::from_leaf_hashes(
+        forged_items.iter().map(hash::LeafHash::of).collect(), merkle::PairHashing::SortedPair).unwrap();
+    assert_ne!(forged_leaves.root(), m_tree.root());
+}
+
+#[test]
+#[allow(clippy::assertions_on_constants)]
 fn merkle1() {
     let names = vec!(String::from("sally"),
         String::from("alice"),
@@ -33,10 +181,14 @@ fn merkle1() {
             println!("Invalid Tree: {}", x);
             assert!(false);
         }
+        merkle::MrklVR::InvalidOrder(x) => {
+            panic!("Invalid Order: {}", x);
+        }
     }
 }
 
 #[test]
+#[allow(clippy::assertions_on_constants)]
 fn merkle2() {
     let mut v = Vec::new();
     for i in (1..10000).step_by(2) {
@@ -64,14 +216,17 @@ fn merkle2() {
             println!("Invalid Tree: {}", x);
             assert!(false);
         }
+        merkle::MrklVR::InvalidOrder(x) => {
+            panic!("Invalid Order: {}", x);
+        }
     }
 
     let to_check = vec!(11.to_string(), 101.to_string());
     for element in &to_check {
-        assert!(m_tree.contains(&element).unwrap());
+        assert!(m_tree.contains(element).unwrap());
     }
 
-    if m_tree.prune(&to_check) {
+    if m_tree.prune(&to_check).is_ok() {
         match m_tree.validate() {
             merkle::MrklVR::InvalidTree(_) => {}
             _ => assert!(false) 
@@ -89,3 +244,934 @@ fn merkle_contains() {
     
     assert!(!m_tree.contains(&2.to_string()).unwrap())
 }
+
+#[test]
+fn merkle_contains_borrowed() {
+    let items: Vec<String> = (0..9).map(|i: u32| format!("item {}", i)).collect();
+    let m_tree = merkle::MerkleTree::construct(items).unwrap();
+
+    assert_eq!(m_tree.contains("item 4"), Ok(true));
+    assert_eq!(m_tree.contains("item 9"), Ok(false));
+    assert_eq!(m_tree.contains(&String::from("item 8")), Ok(true));
+}
+
+#[test]
+fn merkle_from_leaf_hashes() {
+    let mut hashes: Vec<hash::LeafHash> = (0..13).map(|i: u32| hash::LeafHash::of(&i.to_string())).collect();
+    let m_tree = merkle::MerkleTree::from_leaf_hashes(hashes.clone(), merkle::PairHashing::Ordered).unwrap();
+    assert!(m_tree.validate().is_valid());
+    assert_eq!(m_tree.prove(&hashes[0]).unwrap().leaves(), vec!(&hashes[0]));
+
+    hashes.sort();
+    let raw: Vec<String> = hashes.iter().map(|hash| hash.as_str().to_owned()).collect();
+    assert!(merkle::RootBuilder::verify(m_tree.root(), &raw, merkle::PairHashing::Ordered));
+
+    for (index, hash) in hashes.iter().enumerate() {
+        let proof = m_tree.prove_leaf(index).unwrap();
+        assert!(proof.same_root(&m_tree));
+        assert!(proof.validate_pruned().is_valid());
+        assert_eq!(proof.leaves(), vec!(hash));
+    }
+    assert!(m_tree.prove_leaf(13).is_none());
+}
+
+#[test]
+fn merkle_sorted_pair() {
+    let mut v = Vec::new();
+    for i in 0..37 {
+        v.push(i.to_string());
+    }
+    let ordered = merkle::MerkleTree::construct(v.clone()).unwrap();
+    let mut sorted = merkle::MerkleTree::construct_with(v, merkle::PairHashing::SortedPair).unwrap();
+
+    assert_eq!(sorted.pair_hashing(), merkle::PairHashing::SortedPair);
+    assert_ne!(ordered.root(), sorted.root());
+
+    match sorted.validate() {
+        merkle::MrklVR::Valid => {}
+        _ => panic!("Sorted-pair tree should be valid")
+    }
+
+    let (a, b) = (String::from("a"), String::from("b"));
+    let pair = merkle::MerkleTree::construct_with(vec!(a.clone(), b.clone()), merkle::PairHashing::SortedPair).unwrap();
+    assert_eq!(*pair.root(), merkle::PairHashing::SortedPair.combine(hash::LeafHash::of(&b).as_str(), Some(hash::LeafHash::of(&a).as_str())));
+
+    sorted.prune(&[3.to_string(), 30.to_string()]).unwrap();
+    match sorted.validate_pruned() {
+        merkle::MrklVR::Valid => {}
+        _ => panic!("Pruned sorted-pair tree should be valid")
+    }
+}
+
+#[test]
+fn merkle_open_zeppelin() {
+    let oz = merkle::PairHashing::OpenZeppelin;
+
+    // The example from the README of OpenZeppelin's merkle-tree library: `StandardMerkleTree.of` over
+    // [address, uint256] values, whose leaves are keccak256(keccak256(abi.encode(values))).
+    let encode = |address: u8, amount: u64| {
+        let mut encoded = vec!(0u8; 12);
+        encoded.extend_from_slice(&[address; 20]);
+        encoded.extend_from_slice(&[0u8; 24]);
+        encoded.extend_from_slice(&amount.to_be_bytes());
+        encoded
+    };
+    let values = vec!(encode(0x11, 5_000_000_000_000_000_000), encode(0x22, 2_500_000_000_000_000_000));
+    let m_tree = merkle::MerkleTree::construct_with(values.clone(), oz).unwrap();
+    assert_eq!(m_tree.root().as_str(), "d4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77");
+    assert_eq!(m_tree.proof_hashes(&values[0]), Some(vec!(values[1].leaf_hash(oz).into_string())));
+
+    // Leaves passed in as raw bytes32 values are used as they are.
+    let leaves: Vec<hash::LeafHash> = values.iter().map(|value| value.leaf_hash(oz)).collect();
+    let from_leaves = merkle::MerkleTree::from_leaf_hashes(leaves, oz).unwrap();
+    assert_eq!(from_leaves.root(), m_tree.root());
+
+    for n in 1..20 {
+        let items: Vec<String> = (0..n).map(|i: u32| i.to_string()).collect();
+        let m_tree = merkle::MerkleTree::construct_with(items.clone(), oz).unwrap();
+        assert!(m_tree.validate().is_valid());
+
+        for item in &items {
+            let proof = m_tree.proof_hashes(item).unwrap();
+            assert_eq!(oz.process_proof(&item.leaf_hash(oz), &proof), *m_tree.root());
+
+            let pruned = m_tree.prove(item).unwrap();
+            assert!(pruned.validate_pruned().is_valid());
+            assert_eq!(pruned.proof_hashes(item), Some(proof));
+        }
+        assert!(m_tree.proof_hashes("missing").is_none());
+    }
+}
+
+#[test]
+fn merkle_validate_pruned() {
+    let mut v = Vec::new();
+    for i in 0..100 {
+        v.push(i.to_string());
+    }
+    let mut m_tree = merkle::MerkleTree::construct(v).unwrap();
+
+    m_tree.prune(&[7.to_string(), 70.to_string(), 99.to_string()]).unwrap();
+    match m_tree.validate_pruned() {
+        merkle::MrklVR::Valid => {}
+        merkle::MrklVR::InvalidHash(x) | merkle::MrklVR::InvalidTree(x) | merkle::MrklVR::InvalidOrder(x)
+            => panic!("{}", x)
+    }
+}
+
+#[test]
+fn blob_proofs() {
+    use std::io::Cursor;
+
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    let tree = blob::BlobTree::from_reader(Cursor::new(&data), 1000).unwrap();
+
+    assert_eq!(tree.chunk_count(), 10);
+    assert_eq!(tree.len(), 10_000);
+
+    for i in 0..tree.chunk_count() {
+        let proof = tree.prove(i).unwrap();
+        let chunk = &data[i * 1000..(i + 1) * 1000];
+        assert!(proof.verify(&tree.root(), chunk));
+        assert!(!proof.verify(&tree.root(), &data[0..999]));
+        assert_eq!(tree.read_chunk(&mut Cursor::new(&data), i).unwrap(), chunk);
+    }
+    assert!(tree.prove(10).is_none());
+
+    let mut corrupt = data.clone();
+    corrupt[4500] ^= 1;
+    assert!(tree.read_chunk(&mut Cursor::new(&corrupt), 4).is_err());
+    assert!(tree.read_chunk(&mut Cursor::new(&corrupt), 5).is_ok());
+
+    let empty = blob::BlobTree::from_reader(Cursor::new(Vec::new()), 1000).unwrap();
+    assert_eq!(empty.chunk_count(), 1);
+    assert!(empty.prove(0).unwrap().verify(&empty.root(), &[]));
+}
+
+#[test]
+fn blob_resumable_download() {
+    use std::io::Cursor;
+
+    let data: Vec<u8> = (0..2_500u32).map(|i| (i % 7) as u8).collect();
+    let source = blob::BlobTree::from_reader(Cursor::new(&data), 512).unwrap();
+    let root = source.root();
+
+    let hashes = (0..source.chunk_count()).map(|i| source.chunk_hash(i).unwrap()).collect();
+    let tree = blob::BlobTree::from_chunk_hashes(hashes, source.len(), 512).unwrap();
+
+    let mut download = blob::BlobDownload::start(&root, tree, Cursor::new(Vec::new())).unwrap();
+    assert_eq!(download.missing(), vec!(0, 1, 2, 3, 4));
+    assert!(download.write_chunk(1, &data[0..512]).is_err());
+    download.write_chunk(0, &data[0..512]).unwrap();
+    download.write_chunk(2, &data[1024..1536]).unwrap();
+    let partial = download.finish();
+    assert!(partial.is_err());
+
+    let mut sink = vec!(0; 2_500);
+    sink[0..512].copy_from_slice(&data[0..512]);
+    sink[1024..1536].copy_from_slice(&data[1024..1536]);
+
+    let hashes = (0..source.chunk_count()).map(|i| source.chunk_hash(i).unwrap()).collect();
+    let tree = blob::BlobTree::from_chunk_hashes(hashes, source.len(), 512).unwrap();
+    let mut download = blob::BlobDownload::start(&root, tree, Cursor::new(sink)).unwrap();
+    assert_eq!(download.missing(), vec!(1, 3, 4));
+    for i in download.missing() {
+        let end = ::std::cmp::min((i + 1) * 512, data.len());
+        download.write_chunk(i, &data[i * 512..end]).unwrap();
+    }
+    assert_eq!(download.finish().unwrap().into_inner(), data);
+}
+
+#[test]
+fn torrent_pieces_root() {
+    use std::io::Cursor;
+
+    let to_hex = |hash: &[u8]| hash.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    let data = |n: usize| (0..n).map(|i| ((i * 7 + 3) % 256) as u8).collect::<Vec<u8>>();
+
+    // Expected values computed independently from the BEP 52 description.
+    let vectors = vec!(
+        (100_000, 32 * 1024, "778f4951b75b964403a882d646a6181688553d2a3a32bb87bd5641ca61185e76"),
+        (5_000, 16 * 1024, "34398b85297bf7d9dfb59b8d511d8bbb44ab23e891570e4395e7871475fc8afb"),
+        (3 * 16 * 1024, 64 * 1024, "4c36f143205b58011b94261a220b146bede8ae0c0617994d0635cf5786699b4b")
+    );
+
+    for (len, piece_length, root) in vectors {
+        let file = data(len);
+        let hashes = torrent::FileHashes::from_reader(Cursor::new(&file), piece_length).unwrap();
+
+        assert_eq!(to_hex(&hashes.pieces_root), root);
+        assert!(hashes.verify_piece_layer());
+
+        for (i, piece) in file.chunks(piece_length).enumerate() {
+            assert!(hashes.verify_piece(i, piece));
+            let mut corrupt = piece.to_vec();
+            corrupt[0] ^= 1;
+            assert!(!hashes.verify_piece(i, &corrupt));
+        }
+    }
+
+    assert!(torrent::FileHashes::from_reader(Cursor::new(Vec::new()), 16 * 1024).is_err());
+    assert!(torrent::FileHashes::from_reader(Cursor::new(data(10)), 20_000).is_err());
+
+    let empty = torrent::FileHashes { piece_length: 16 * 1024, len: 0, pieces_root: [0; 32], piece_layer: Vec::new() };
+    assert_eq!(empty.piece_count(), 0);
+    assert!(!empty.verify_piece(0, &[]));
+}
+
+#[test]
+fn merkle_root_builder() {
+    for &pair_hashing in &[merkle::PairHashing::Ordered, merkle::PairHashing::SortedPair, merkle::PairHashing::OpenZeppelin] {
+        for n in 1..40 {
+            let mut v: Vec<String> = (0..n).map(|i: u32| i.to_string()).collect();
+            let m_tree = merkle::MerkleTree::construct_with(v.clone(), pair_hashing).unwrap();
+
+            v.sort();
+            let hashes: Vec<String> = v.iter().map(|item| item.leaf_hash(pair_hashing).into_string()).collect();
+            assert!(merkle::RootBuilder::verify(m_tree.root(), &hashes, pair_hashing));
+            assert!(!merkle::RootBuilder::verify(m_tree.root(), &hashes[1..], pair_hashing));
+        }
+    }
+    assert!(merkle::RootBuilder::new(merkle::PairHashing::Ordered).finish().is_none());
+}
+
+#[test]
+fn merkle_prune_errors() {
+    let mut m_tree = merkle::MerkleTree::construct((0..10).map(|i: u32| i.to_string()).collect()).unwrap();
+    let root = m_tree.root().clone();
+
+    assert_eq!(m_tree.prune(&[]), Err(merkle::PruneError::NothingToKeep));
+    assert_eq!(
+        m_tree.prune(&[3.to_string(), 11.to_string(), 4.to_string(), 12.to_string()]),
+        Err(merkle::PruneError::MissingItems(vec!(11.to_string(), 12.to_string())))
+    );
+
+    // A failed prune leaves the tree untouched.
+    match m_tree.validate() {
+        merkle::MrklVR::Valid => {}
+        _ => panic!("Tree should still be unpruned")
+    }
+    assert_eq!(*m_tree.root(), root);
+
+    m_tree.prune(&[3.to_string()]).unwrap();
+    match m_tree.prune(&[3.to_string()]) {
+        Err(merkle::PruneError::InvalidTree(_)) => {}
+        _ => panic!("A pruned tree cannot be pruned again")
+    }
+}
+
+#[test]
+fn merkle_graft() {
+    let items: Vec<String> = (0..64).map(|i| format!("{:02}", i)).collect();
+    let mut m_tree = merkle::MerkleTree::construct(items.clone()).unwrap();
+    let root = m_tree.root().clone();
+
+    m_tree.prune(&items[0..1]).unwrap();
+    assert!(m_tree.contains(&items[5]).is_err());
+
+    // Subtrees built from the wrong items, or of the wrong shape, are rejected.
+    assert_eq!(m_tree.graft(merkle::MerkleTree::construct(items[4..7].to_vec()).unwrap()), Err(merkle::GraftError::HashMismatch));
+    assert_eq!(m_tree.graft(merkle::MerkleTree::construct(items[4..6].to_vec()).unwrap()), Err(merkle::GraftError::HashMismatch));
+    assert_eq!(m_tree.graft_leaf(String::from("01x")), Err(merkle::GraftError::HashMismatch));
+    assert_eq!(m_tree.graft_leaf(items[0].clone()), Err(merkle::GraftError::NoPrunedBranch));
+    let sorted_pair = merkle::MerkleTree::construct_with(items[4..8].to_vec(), merkle::PairHashing::SortedPair).unwrap();
+    assert_eq!(m_tree.graft(sorted_pair), Err(merkle::GraftError::PairHashingMismatch));
+
+    m_tree.graft(merkle::MerkleTree::construct(items[4..8].to_vec()).unwrap()).unwrap();
+    assert!(m_tree.contains(&items[5]).unwrap());
+    match m_tree.validate_pruned() {
+        merkle::MrklVR::Valid => {}
+        _ => panic!("Grafted tree should be a valid pruned tree")
+    }
+
+    m_tree.graft_leaf(items[1].clone()).unwrap();
+    for &(start, end) in &[(2, 4), (8, 16), (16, 32), (32, 64)] {
+        m_tree.graft(merkle::MerkleTree::construct(items[start..end].to_vec()).unwrap()).unwrap();
+    }
+
+    match m_tree.validate() {
+        merkle::MrklVR::Valid => {}
+        _ => panic!("Fully grafted tree should be valid")
+    }
+    assert_eq!(*m_tree.root(), root);
+}
+
+#[test]
+fn merkle_leaves() {
+    let items: Vec<String> = (0..13).map(|i: u32| format!("{:02}", i)).rev().collect();
+    let mut m_tree = merkle::MerkleTree::construct(items.clone()).unwrap();
+
+    let mut sorted = items;
+    sorted.sort();
+    assert_eq!(m_tree.leaves(), sorted.iter().collect::<Vec<_>>());
+
+    m_tree.prune(&sorted[3..5]).unwrap();
+    assert_eq!(m_tree.leaves(), sorted[3..5].iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn merkle_prove() {
+    let items: Vec<String> = (0..13).map(|i: u32| format!("{:02}", i)).collect();
+    let mut m_tree = merkle::MerkleTree::construct(items.clone()).unwrap();
+
+    for item in &items {
+        let mut pruned = m_tree.clone();
+        pruned.prune(::std::slice::from_ref(item)).unwrap();
+        assert!(m_tree.prove(item) == Some(pruned));
+    }
+    assert!(m_tree.prove("13").is_none());
+
+    for len in 1..40 {
+        let tree = merkle::MerkleTree::construct((0..len).map(|i| format!("{:02}", i)).collect()).unwrap();
+        for (index, leaf) in tree.leaves().into_iter().enumerate() {
+            assert!(tree.prove_leaf(index) == tree.prove(leaf));
+            assert!(tree.prove_leaf(index).is_some());
+        }
+        assert!(tree.prove_leaf(len).is_none());
+    }
+
+    m_tree.prune(&items[3..5]).unwrap();
+    assert!(m_tree.prove(&items[4]).unwrap().validate_pruned().is_valid());
+    assert!(m_tree.prove(&items[7]).is_none());
+}
+
+#[test]
+fn merkle_subtree() {
+    let items: Vec<String> = (0..64).map(|i| format!("{:02}", i)).collect();
+    let mut m_tree = merkle::MerkleTree::construct(items.clone()).unwrap();
+    let root = m_tree.root().clone();
+
+    let middle = merkle::MerkleTree::construct(items[8..16].to_vec()).unwrap();
+    assert!(m_tree.subtree(&root).is_some());
+    assert_eq!(m_tree.subtree(middle.root()).unwrap().root(), middle.root());
+    assert!(m_tree.subtree(&hash::MerkleRoot::tagged("missing")).is_none());
+    assert!(m_tree.extract_subtree(&root).is_none());
+
+    let extracted = m_tree.extract_subtree(middle.root()).unwrap();
+    assert!(extracted.contains(&items[9]).unwrap());
+    assert!(m_tree.contains(&items[9]).is_err());
+    assert!(m_tree.subtree(middle.root()).is_none());
+    match m_tree.validate_pruned() {
+        merkle::MrklVR::Valid => {}
+        _ => panic!("Tree with an extracted subtree should be a valid pruned tree")
+    }
+
+    m_tree.graft(extracted).unwrap();
+    match m_tree.validate() {
+        merkle::MrklVR::Valid => {}
+        _ => panic!("Tree should be valid once the subtree is grafted back")
+    }
+    assert_eq!(*m_tree.root(), root);
+}
+
+#[test]
+fn merkle_equality() {
+    let items: Vec<String> = (0..50).map(|i| format!("{:02}", i)).collect();
+    let m_tree = merkle::MerkleTree::construct(items.clone()).unwrap();
+
+    let same = merkle::MerkleTree::construct(items.clone()).unwrap();
+    assert!(m_tree == same);
+    assert!(m_tree.same_root(&same));
+    assert_eq!(m_tree.first_divergent_leaf(&same), None);
+
+    let mut changed = items.clone();
+    changed[37] = String::from("37x");
+    let changed = merkle::MerkleTree::construct(changed).unwrap();
+    assert!(m_tree != changed);
+    assert!(!m_tree.same_root(&changed));
+    assert_eq!(m_tree.first_divergent_leaf(&changed), Some(37));
+    assert_eq!(changed.first_divergent_leaf(&m_tree), Some(37));
+
+    for &len in &[10, 16, 49] {
+        let shorter = merkle::MerkleTree::construct(items[..len].to_vec()).unwrap();
+        assert_eq!(m_tree.first_divergent_leaf(&shorter), Some(len));
+        assert_eq!(shorter.first_divergent_leaf(&m_tree), Some(len));
+    }
+
+    let mut pruned = merkle::MerkleTree::construct(items.clone()).unwrap();
+    pruned.prune(&items[20..21]).unwrap();
+    assert!(m_tree != pruned);
+    assert!(m_tree.same_root(&pruned));
+    assert_eq!(m_tree.first_divergent_leaf(&pruned), None);
+}
+
+/*
+ * A Hashable item whose ordering can be reversed after a tree has been constructed,
+ * which lets us build trees whose leaves are out of order.
+ */
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Flippable(u32);
+
+thread_local!(static FLIPPED: ::std::cell::Cell<bool> = const { ::std::cell::Cell::new(false) });
+
+/*
+ * Items listed here hash differently, which lets us corrupt leaves after construction.
+ */
+thread_local!(static CORRUPTED: ::std::cell::RefCell<Vec<u32>> = const { ::std::cell::RefCell::new(Vec::new()) });
+
+impl Hashable for Flippable {
+    fn get_hash(&self) -> String {
+        if CORRUPTED.with(|corrupted| corrupted.borrow().contains(&self.0)) {
+            format!("corrupted {}", self.0).get_hash()
+        } else {
+            self.0.to_string().get_hash()
+        }
+    }
+}
+
+impl PartialOrd for Flippable {
+    fn partial_cmp(&self, other: &Self) -> Option<::std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Flippable {
+    fn cmp(&self, other: &Self) -> ::std::cmp::Ordering {
+        if FLIPPED.with(|flipped| flipped.get()) {
+            other.0.cmp(&self.0)
+        } else {
+            self.0.cmp(&other.0)
+        }
+    }
+}
+
+#[test]
+fn merkle_invalid_order() {
+    let m_tree = merkle::MerkleTree::construct((0..20).map(Flippable).collect()).unwrap();
+    match m_tree.validate() {
+        merkle::MrklVR::Valid => {}
+        _ => panic!("Sorted tree should be valid")
+    }
+
+    FLIPPED.with(|flipped| flipped.set(true));
+    let result = m_tree.validate();
+    FLIPPED.with(|flipped| flipped.set(false));
+
+    match result {
+        merkle::MrklVR::InvalidOrder(error) => {
+            assert_eq!(error.code, merkle::ErrorCode::OutOfOrder);
+            assert_eq!(error.height, 0);
+            assert_eq!(error.path, vec!(merkle::Side::Left; 4));
+        }
+        _ => panic!("Out of order tree should be reported as InvalidOrder")
+    }
+}
+
+#[test]
+fn merkle_validation_errors() {
+    let items: Vec<String> = (0..8).map(|i: u32| i.to_string()).collect();
+    let mut m_tree = merkle::MerkleTree::construct(items.clone()).unwrap();
+
+    assert!(m_tree.validate().is_valid());
+    let result: Result<(), merkle::ValidationError> = m_tree.validate().into();
+    assert_eq!(result, Ok(()));
+
+    m_tree.prune(&items[5..6]).unwrap();
+    assert!(m_tree.validate_pruned().is_valid());
+
+    let error = match m_tree.validate() {
+        merkle::MrklVR::InvalidTree(error) => error,
+        _ => panic!("Pruned tree should fail full validation")
+    };
+    assert_eq!(error.code, merkle::ErrorCode::UnexpectedPartial);
+    assert_eq!(error.code.as_u16(), 4);
+    assert_eq!(error.height, 2);
+    assert!(error.path.is_empty());
+
+    let result: Result<(), merkle::ValidationError> = m_tree.validate().into();
+    assert_eq!(result, Err(error));
+}
+
+#[test]
+fn merkle_validate_full() {
+    let m_tree = merkle::MerkleTree::construct((0..20).map(Flippable).collect()).unwrap();
+    assert!(m_tree.validate_full().is_valid());
+
+    CORRUPTED.with(|corrupted| *corrupted.borrow_mut() = vec!(3, 11));
+    let report = m_tree.validate_full();
+    CORRUPTED.with(|corrupted| corrupted.borrow_mut().clear());
+
+    assert_eq!(report.problems.len(), 2);
+    for (problem, item) in report.problems.iter().zip(&[3, 11]) {
+        assert_eq!(problem.code, merkle::ErrorCode::LeafHashMismatch);
+        assert_eq!(problem.height, 0);
+        assert_eq!(problem.path.last(), Some(&merkle::Side::Right));
+        assert_eq!(problem.expected, Some(hash::LeafHash::tagged(&format!("corrupted {}", item).get_hash()).into_string()));
+        assert_eq!(problem.actual, Some(hash::LeafHash::tagged(&item.to_string().get_hash()).into_string()));
+    }
+
+    FLIPPED.with(|flipped| flipped.set(true));
+    let report = m_tree.validate_full();
+    FLIPPED.with(|flipped| flipped.set(false));
+
+    assert!(report.problems.len() > 1);
+    assert!(report.problems.iter().all(|p| p.code == merkle::ErrorCode::OutOfOrder));
+
+    let items: Vec<String> = (0..8).map(|i: u32| i.to_string()).collect();
+    let mut m_tree = merkle::MerkleTree::construct(items.clone()).unwrap();
+    m_tree.prune(&items[5..6]).unwrap();
+
+    assert!(m_tree.validate_full_pruned().is_valid());
+    let report = m_tree.validate_full();
+    assert!(!report.is_valid());
+    assert!(report.problems.iter().all(|p| p.code == merkle::ErrorCode::UnexpectedPartial));
+}
+
+#[test]
+fn merkle_repair() {
+    use merkle::Side::{Left, Right};
+
+    let items: Vec<Flippable> = (0..20).map(Flippable).collect();
+    let mut m_tree = merkle::MerkleTree::construct(items.clone()).unwrap();
+    let root = m_tree.root().clone();
+    let report = m_tree.validate_full();
+    assert_eq!(m_tree.repair(&report, &[]), Ok(()));
+    assert_eq!(*m_tree.root(), root);
+
+    // Corrupt the stored hash of leaf 1 and the mrkl_root of the node above leaves 8 to 15.
+    m_tree.corrupt(&[Left, Left, Left, Left, Right], &String::from("rot").get_hash());
+    m_tree.corrupt(&[Left, Right], &String::from("rot").get_hash());
+    let report = m_tree.validate_full();
+    assert_eq!(report.problems.len(), 4);
+
+    let error = m_tree.repair(&report, &items[2..]).unwrap_err();
+    assert_eq!(error.code, merkle::ErrorCode::LeafHashMismatch);
+    assert_eq!(error.path, vec!(Left, Left, Left, Left, Right));
+    assert_eq!(m_tree.validate_full(), report);
+
+    assert_eq!(m_tree.repair(&report, &items[1..2]), Ok(()));
+    assert!(m_tree.validate().is_valid());
+    assert_eq!(*m_tree.root(), root);
+
+    // An item which now hashes differently can't be trusted, even if an original matches it.
+    CORRUPTED.with(|corrupted| *corrupted.borrow_mut() = vec!(11));
+    let report = m_tree.validate_full();
+    assert_eq!(report.problems.len(), 1);
+    let error = m_tree.repair(&report, &[Flippable(11)]).unwrap_err();
+    assert_eq!(error.code, merkle::ErrorCode::RootMismatch);
+    assert_eq!(m_tree.validate_full(), report);
+
+    CORRUPTED.with(|corrupted| corrupted.borrow_mut().clear());
+    assert!(m_tree.validate().is_valid());
+    assert_eq!(*m_tree.root(), root);
+}
+
+#[test]
+fn merkle_validate_deep() {
+    let depth = 20_000;
+    let item = String::from("deep");
+
+    let m_tree = merkle::MerkleTree::degenerate(item.clone(), depth, None, merkle::PairHashing::Ordered);
+    assert!(m_tree.validate().is_valid());
+    assert!(m_tree.validate_pruned().is_valid());
+    assert_eq!(m_tree.contains(&item), Ok(true));
+    assert_eq!(m_tree.contains("shallow"), Ok(false));
+
+    let sibling = String::from("sibling").get_hash();
+    let mut pruned = merkle::MerkleTree::degenerate(item.clone(), depth, Some(&sibling), merkle::PairHashing::Ordered);
+    assert!(pruned.validate_pruned().is_valid());
+    assert!(!pruned.validate().is_valid());
+    assert_eq!(pruned.contains(&item), Ok(true));
+    assert!(pruned.contains("zzz").is_err());
+    assert_eq!(pruned.graft_leaf(String::from("zzz")), Err(merkle::GraftError::HashMismatch));
+}
+
+#[test]
+fn merkle_validate_full_large() {
+    let m_tree = merkle::MerkleTree::construct((0..5000).map(Flippable).collect()).unwrap();
+    assert!(m_tree.validate_full().is_valid());
+
+    CORRUPTED.with(|corrupted| *corrupted.borrow_mut() = vec!(4999));
+    let report = m_tree.validate_full();
+    CORRUPTED.with(|corrupted| corrupted.borrow_mut().clear());
+
+    assert_eq!(report.problems.len(), 1);
+    assert_eq!(report.problems[0].path.len(), 13);
+    assert_eq!(report.problems[0].path.last(), Some(&merkle::Side::Right));
+}
+
+#[test]
+fn merkle_map() {
+    let entries: Vec<(String, String)> = (0..10u32)
+        .map(|i| (format!("key{}", i), format!("value{}", i * i)))
+        .collect();
+    let m_map = map::MerkleMap::construct(entries.clone()).unwrap();
+    assert!(m_map.tree().validate().is_valid());
+
+    assert_eq!(m_map.get(&String::from("key3")), Some(&String::from("value9")));
+    assert_eq!(m_map.get(&String::from("key10")), None);
+    assert!(m_map.get_with_proof(&String::from("missing")).is_none());
+
+    let key = String::from("key7");
+    let (value, proof) = m_map.get_with_proof(&key).unwrap();
+    assert_eq!(*value, "value49");
+    assert_eq!(proof.leaves().len(), 1);
+    assert!(map::MerkleMap::verify(m_map.root(), &key, value, &proof));
+    assert!(!map::MerkleMap::verify(m_map.root(), &key, &String::from("value50"), &proof));
+    assert!(!map::MerkleMap::verify(m_map.root(), &String::from("key6"), &String::from("value36"), &proof));
+
+    let other = map::MerkleMap::construct(vec!((key.clone(), String::from("value50")))).unwrap();
+    assert!(!map::MerkleMap::verify(other.root(), &key, value, &proof));
+
+    // Replacing every value in place gives the same tree as building the map from the new values.
+    let mut replaced = map::MerkleMap::construct(entries.clone()).unwrap();
+    let mut updated = entries.clone();
+    for (key, value) in updated.iter_mut() {
+        value.push('!');
+        assert!(replaced.insert(key.clone(), value.clone()).is_some());
+        assert!(replaced.tree().validate().is_valid());
+    }
+    assert!(*replaced.tree() == *map::MerkleMap::construct(updated).unwrap().tree());
+
+    let mut duplicates = entries;
+    duplicates.push((String::from("key3"), String::from("other")));
+    assert!(map::MerkleMap::construct(duplicates).is_err());
+}
+
+#[test]
+fn notary_receipts() {
+    let documents: Vec<String> = (0..5).map(|i: u32| i.to_string().get_hash()).collect();
+    let mut notary = notary::Notary::new();
+    assert!(notary.seal(100).is_err());
+
+    for document in &documents[..3] {
+        notary.submit(document.clone());
+    }
+    let first = notary.seal(100).unwrap().hash();
+    assert_eq!(notary.pending(), 0);
+
+    for document in &documents[3..] {
+        notary.submit(document.clone());
+    }
+    assert!(notary.seal(99).is_err());
+    let second = notary.seal(200).unwrap().hash();
+
+    assert_eq!(notary.headers().len(), 2);
+    assert_eq!(notary.headers()[1].previous, Some(first.clone()));
+    assert!(notary::Notary::verify_chain(notary.headers()));
+
+    let receipt = notary.receipt(&documents[1]).unwrap();
+    assert_eq!(receipt.header.index, 0);
+    assert_eq!(receipt.proof.leaves(), vec!(&documents[1]));
+    assert!(receipt.verify(&first));
+    assert!(!receipt.verify(&second));
+
+    let receipt = notary.receipt(&documents[4]).unwrap();
+    assert_eq!(receipt.header.timestamp, 200);
+    assert!(receipt.verify(&second));
+
+    let mut forged = receipt.clone();
+    forged.document_hash = documents[3].clone();
+    assert!(!forged.verify(&second));
+
+    let mut forged = receipt;
+    forged.header.timestamp = 150;
+    assert!(!forged.verify(&second));
+
+    assert!(notary.receipt(&String::from("unknown").get_hash()).is_none());
+
+    let mut headers = notary.headers().to_vec();
+    headers.swap(0, 1);
+    assert!(!notary::Notary::verify_chain(&headers));
+}
+
+#[test]
+fn iblt_reconcile() {
+    let shared: Vec<String> = (0..1000).map(|i: u32| i.to_string()).collect();
+    let mut ours = shared.clone();
+    let mut theirs = shared;
+    ours.extend((0..10).map(|i| format!("ours {}", i)));
+    theirs.extend((0..15).map(|i| format!("theirs {}", i)));
+
+    let local = iblt::Iblt::from_items(&ours, 60, 3).unwrap();
+    let remote = iblt::Iblt::from_items(&theirs, 60, 3).unwrap();
+    let difference = iblt::Iblt::reconcile(&local, &remote).unwrap();
+
+    let mut local_only: Vec<iblt::Key> = ours[1000..].iter().map(iblt::Iblt::key).collect();
+    let mut remote_only: Vec<iblt::Key> = theirs[1000..].iter().map(iblt::Iblt::key).collect();
+    local_only.sort();
+    remote_only.sort();
+    assert_eq!(difference.local_only, local_only);
+    assert_eq!(difference.remote_only, remote_only);
+
+    let empty = iblt::Iblt::reconcile(&local, &local).unwrap();
+    assert_eq!(empty, iblt::SetDifference::default());
+
+    let small_local = iblt::Iblt::from_items(&ours, 6, 3).unwrap();
+    let small_remote = iblt::Iblt::from_items(&theirs, 6, 3).unwrap();
+    assert!(iblt::Iblt::reconcile(&small_local, &small_remote).is_err());
+    assert!(iblt::Iblt::reconcile(&small_local, &remote).is_err());
+
+    let mut table = iblt::Iblt::with_capacity(4);
+    let key = iblt::Iblt::key(&String::from("item"));
+    table.insert(&key);
+    assert_eq!(table.clone().decode().unwrap().local_only, vec!(key));
+    table.remove(&key);
+    assert_eq!(table.decode().unwrap(), iblt::SetDifference::default());
+
+    assert!(iblt::Iblt::new(0, 3).is_err());
+}
+
+#[test]
+fn cuckoo_filter() {
+    use filter::ProbabilisticSet;
+
+    let items: Vec<String> = (0..1000).map(|i: u32| i.to_string()).collect();
+    let absent: Vec<String> = (1000..2000).map(|i: u32| i.to_string()).collect();
+
+    let mut filter = filter::CuckooFilter::new(2000);
+    assert!(filter.is_empty());
+    for item in &items {
+        filter.insert(item).unwrap();
+    }
+    assert_eq!(filter.len(), 1000);
+    assert!(items.iter().all(|item| filter.contains(item)));
+    assert!(absent.iter().filter(|item| filter.contains(*item)).count() < 10);
+
+    for item in &items[..500] {
+        assert!(filter.remove(item));
+    }
+    assert_eq!(filter.len(), 500);
+    assert!(items[500..].iter().all(|item| filter.contains(item)));
+    assert!(items[..500].iter().filter(|item| filter.contains(*item)).count() < 10);
+
+    let mut small = filter::CuckooFilter::new(16);
+    let mut inserted = Vec::new();
+    for item in &items {
+        if small.insert(item).is_err() { break; }
+        inserted.push(item.clone());
+    }
+    assert!(inserted.len() < items.len());
+    assert!(inserted.len() >= small.capacity() / 2);
+    assert!(inserted.iter().all(|item| small.contains(item)));
+
+    assert!(small.remove(&inserted[0]));
+    assert!(small.insert(&String::from("new item")).is_ok());
+}
+
+#[test]
+fn known_answer_vectors() {
+    use std::io::Cursor;
+
+    let to_hex = |hash: &[u8]| hash.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+
+    let merkle_roots = testvectors::merkle_roots();
+    assert!(!merkle_roots.is_empty());
+    for vector in merkle_roots {
+        let leaf_hashes: Vec<String> = {
+            let mut leaves = vector.leaves.clone();
+            leaves.sort();
+            leaves.iter().map(|leaf| leaf.leaf_hash(vector.pair_hashing).into_string()).collect()
+        };
+        assert!(merkle::RootBuilder::verify(&vector.root, &leaf_hashes, vector.pair_hashing));
+
+        let tree = merkle::MerkleTree::construct_with(vector.leaves, vector.pair_hashing).unwrap();
+        assert_eq!(*tree.root(), vector.root);
+    }
+
+    for vector in testvectors::blob_roots() {
+        let data = testvectors::vector_data(vector.len);
+        let tree = blob::BlobTree::from_reader(Cursor::new(&data), vector.chunk_size).unwrap();
+        assert_eq!(tree.root(), vector.root);
+    }
+
+    for vector in testvectors::chunk_proofs() {
+        let data = testvectors::vector_data(vector.len);
+        let tree = blob::BlobTree::from_reader(Cursor::new(&data), vector.chunk_size).unwrap();
+        let index = vector.proof.index;
+        assert_eq!(tree.prove(index).unwrap(), vector.proof);
+
+        let start = index * vector.chunk_size;
+        let chunk = &data[start..start + tree.chunk_len(index)];
+        assert!(vector.proof.verify(&tree.root(), chunk));
+    }
+
+    for vector in testvectors::pieces_roots() {
+        let data = testvectors::vector_data(vector.len);
+        let hashes = torrent::FileHashes::from_reader(Cursor::new(&data), vector.piece_length).unwrap();
+        assert_eq!(to_hex(&hashes.pieces_root), vector.pieces_root);
+    }
+}
+
+#[test]
+fn state_commitment() {
+    use state::StateCommitment;
+
+    fn apply<S: StateCommitment<String, String>>(state: &mut S, updates: &[(&str, &str)]) -> Vec<Option<String>> {
+        updates.iter().map(|&(key, value)| state.insert(String::from(key), String::from(value))).collect()
+    }
+
+    let mut m_map = map::MerkleMap::construct(vec!((String::from("b"), String::from("1")))).unwrap();
+    let previous = apply(&mut m_map, &[("a", "2"), ("c", "3"), ("b", "4")]);
+    assert_eq!(previous, vec!(None, None, Some(String::from("1"))));
+
+    let keys: Vec<&String> = m_map.tree().leaves().into_iter().map(|entry| entry.key()).collect();
+    assert_eq!(keys, vec!("a", "b", "c"));
+    assert!(m_map.tree().validate().is_valid());
+
+    let root = StateCommitment::root(&m_map);
+    let key = String::from("b");
+    let proof = m_map.prove(&key).unwrap();
+    assert_eq!(StateCommitment::get(&m_map, &key), Some(&String::from("4")));
+    assert!(<map::MerkleMap<String, String> as StateCommitment<_, _>>::verify(&root, &key, &String::from("4"), &proof));
+    assert!(!<map::MerkleMap<String, String> as StateCommitment<_, _>>::verify(&root, &key, &String::from("1"), &proof));
+
+    let rebuilt = map::MerkleMap::construct(vec!(
+        (String::from("c"), String::from("3")),
+        (String::from("a"), String::from("2")),
+        (String::from("b"), String::from("4"))
+    )).unwrap();
+    assert_eq!(*rebuilt.root(), root);
+}
+
+#[test]
+fn merkle_construct_progress() {
+    use std::sync::{Arc, Mutex};
+
+    let items: Vec<String> = (0..1000).map(|i: u32| i.to_string()).collect();
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let recorder = reports.clone();
+    let handle = progress::ProgressHandle::with_callback(move |percent| recorder.lock().unwrap().push(percent));
+
+    let m_tree = merkle::MerkleTree::construct_with_progress(items.clone(), merkle::PairHashing::Ordered, &handle).unwrap();
+    assert!(m_tree.same_root(&merkle::MerkleTree::construct(items.clone()).unwrap()));
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(*reports, (0..101).collect::<Vec<u8>>());
+
+    let handle = progress::ProgressHandle::with_callback(|_| {});
+    let canceller = handle.clone();
+    canceller.cancel();
+    assert!(handle.is_cancelled());
+    let result = merkle::MerkleTree::construct_with_progress(items, merkle::PairHashing::Ordered, &handle);
+    assert_eq!(result.err().as_deref(), Some(progress::CANCELLED));
+}
+
+#[test]
+fn merkle_sum_tree() {
+    for n in 1..20 {
+        let leaves: Vec<(String, u64)> = (0..n).map(|i: u64| (format!("user {}", i), i * 10 + 1)).collect();
+        let tree = sum::MerkleSumTree::construct(leaves.clone()).unwrap();
+        assert_eq!(tree.total(), leaves.iter().map(|&(_, amount)| amount).sum::<u64>());
+        assert_eq!(tree.leaf_count(), n as usize);
+
+        for (index, &(ref id, amount)) in leaves.iter().enumerate() {
+            let proof = tree.prove(index).unwrap();
+            assert!(proof.verify(tree.root(), id, amount));
+            assert!(!proof.verify(tree.root(), id, amount + 1));
+            assert!(!proof.verify(tree.root(), "someone else", amount));
+        }
+        assert!(tree.prove(n as usize).is_none());
+    }
+
+    let leaves: Vec<(String, u64)> = (0..5).map(|i: u64| (i.to_string(), 100)).collect();
+    let tree = sum::MerkleSumTree::construct(leaves).unwrap();
+    let mut proof = tree.prove(2).unwrap();
+
+    let mut inflated = tree.root().clone();
+    inflated.sum += 1;
+    assert!(!proof.verify(&inflated, "2", 100));
+
+    if let Some(ref mut sibling) = proof.siblings[0] {
+        sibling.sum -= 1;
+    }
+    assert!(!proof.verify(tree.root(), "2", 100));
+
+    assert!(sum::MerkleSumTree::construct(Vec::new()).is_err());
+    assert!(sum::MerkleSumTree::construct(vec!((String::from("a"), u64::MAX), (String::from("b"), 1))).is_err());
+
+    let blinded = vec!((String::from("a"), u64::MAX), (String::from("b"), 11));
+    let tree = sum::MerkleSumTree::construct_with(blinded, sum::SumMode::Blinded).unwrap();
+    assert_eq!(tree.mode(), sum::SumMode::Blinded);
+    assert_eq!(tree.total(), 10);
+    let proof = tree.prove(1).unwrap();
+    assert!(proof.verify_with(tree.root(), "b", 11, sum::SumMode::Blinded));
+    assert!(!proof.verify(tree.root(), "b", 11));
+}
+
+#[test]
+fn liabilities_proofs() {
+    let balances: Vec<(String, u64)> = (0..10).map(|i: u64| (format!("user {}", i), (i + 1) * 1000)).collect();
+    let m_liabilities = liabilities::Liabilities::construct(balances.clone(), 3, "seed").unwrap();
+    let root = m_liabilities.root().hash.clone();
+    let total = m_liabilities.total();
+
+    assert_eq!(total, 55_000);
+    assert_eq!(m_liabilities.tree().leaf_count(), 30);
+    assert_eq!(m_liabilities.tree().mode(), sum::SumMode::Blinded);
+
+    for &(ref user, amount) in &balances {
+        let proof = m_liabilities.proof(user).unwrap();
+        assert_eq!(proof.amount, amount);
+        assert_eq!(proof.shares.len(), 3);
+        assert!(proof.verify(&root, total));
+        assert!(!proof.verify(&root, total + 1));
+
+        let mut stolen = proof.clone();
+        stolen.user = String::from("someone else");
+        assert!(!stolen.verify(&root, total));
+
+        let mut short = proof.clone();
+        short.shares.pop();
+        assert!(!short.verify(&root, total));
+
+        let mut unblinded = proof.clone();
+        unblinded.shares[0].blinding = 0;
+        assert!(!unblinded.verify(&root, total));
+
+        for share in &proof.shares {
+            assert!(share.proof.siblings.iter().flatten().all(|sibling| sibling.sum > total));
+        }
+    }
+    assert!(m_liabilities.proof("nobody").is_none());
+
+    let again = liabilities::Liabilities::construct(balances.clone(), 3, "seed").unwrap();
+    let other = liabilities::Liabilities::construct(balances.clone(), 3, "other seed").unwrap();
+    assert_eq!(again.root(), m_liabilities.root());
+    assert_ne!(other.root().hash, root);
+
+    assert!(liabilities::Liabilities::construct(balances.clone(), 0, "seed").is_err());
+    assert!(liabilities::Liabilities::construct(Vec::new(), 3, "seed").is_err());
+    assert!(liabilities::Liabilities::construct(vec!((String::from("a"), 1), (String::from("a"), 2)), 3, "seed").is_err());
+}
@@ -0,0 +1,157 @@
+/*!
+ * Known-answer test vectors. Currently supports:
+ * - `MerkleTree` roots under every `PairHashing` mode
+ * - `BlobTree` roots and `ChunkProof` fixtures
+ * - BEP 52 pieces roots
+ *
+ * The vectors live in plain text files under `testvectors/` at the root of the repository, so
+ * implementations in other languages can read them directly. Each line holds one vector as
+ * space separated fields, and lines starting with `#` are comments describing the format.
+ * This module parses the same files, and the crate's tests check every vector against it.
+ *
+ * Blob and torrent vectors are generated from `vector_data`.
+ *
+ * # Examples
+ *
+ * ```
+ * use newton::merkle::MerkleTree;
+ * use newton::testvectors;
+ *
+ * for vector in testvectors::merkle_roots() {
+ *     let tree = MerkleTree::construct_with(vector.leaves, vector.pair_hashing).unwrap();
+ *     assert_eq!(*tree.root(), vector.root);
+ * }
+ * ```
+ */
+
+use blob::ChunkProof;
+use hash::MerkleRoot;
+use merkle::PairHashing;
+
+const MERKLE_ROOTS: &str = include_str!("../testvectors/merkle_roots.txt");
+const BLOB_ROOTS: &str = include_str!("../testvectors/blob_roots.txt");
+const CHUNK_PROOFS: &str = include_str!("../testvectors/chunk_proofs.txt");
+const TORRENT_ROOTS: &str = include_str!("../testvectors/torrent_roots.txt");
+
+/**
+ * The root of a `MerkleTree<String>` built from `leaves` with `pair_hashing`.
+ */
+#[derive(Clone, Debug)]
+pub struct MerkleRootVector {
+    pub pair_hashing: PairHashing,
+    pub leaves: Vec<String>,
+    pub root: MerkleRoot
+}
+
+/**
+ * The root of a `BlobTree` over `vector_data(len)` split into chunks of `chunk_size` bytes.
+ */
+#[derive(Clone, Debug)]
+pub struct BlobRootVector {
+    pub chunk_size: usize,
+    pub len: usize,
+    pub root: MerkleRoot
+}
+
+/**
+ * The `ChunkProof` for one chunk of `vector_data(len)` split into chunks of `chunk_size` bytes.
+ */
+#[derive(Clone, Debug)]
+pub struct ChunkProofVector {
+    pub chunk_size: usize,
+    pub len: usize,
+    pub proof: ChunkProof
+}
+
+/**
+ * The BEP 52 `pieces root` of `vector_data(len)` with pieces of `piece_length` bytes, as a hex string.
+ */
+#[derive(Clone, Debug)]
+pub struct PiecesRootVector {
+    pub piece_length: usize,
+    pub len: usize,
+    pub pieces_root: String
+}
+
+/**
+ * Returns the `len` bytes the blob and torrent vectors are computed over. Byte `i` is
+ * `(i * 7 + 3) % 256`.
+ */
+pub fn vector_data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| ((i * 7 + 3) % 256) as u8).collect()
+}
+
+/**
+ * # Panics
+ * Will panic if `testvectors/merkle_roots.txt` is malformed.
+ */
+pub fn merkle_roots() -> Vec<MerkleRootVector> {
+    records(MERKLE_ROOTS).map(|fields| {
+        let pair_hashing = match fields[0] {
+            "ordered" => PairHashing::Ordered,
+            "sorted_pair" => PairHashing::SortedPair,
+            "open_zeppelin" => PairHashing::OpenZeppelin,
+            other => panic!("Unknown pair hashing mode {}", other)
+        };
+        MerkleRootVector {
+            pair_hashing,
+            leaves: fields[1].split(',').map(String::from).collect(),
+            root: MerkleRoot::from_hex(String::from(fields[2]))
+        }
+    }).collect()
+}
+
+/**
+ * # Panics
+ * Will panic if `testvectors/blob_roots.txt` is malformed.
+ */
+pub fn blob_roots() -> Vec<BlobRootVector> {
+    records(BLOB_ROOTS).map(|fields| BlobRootVector {
+        chunk_size: number(fields[0]),
+        len: number(fields[1]),
+        root: MerkleRoot::from_hex(String::from(fields[2]))
+    }).collect()
+}
+
+/**
+ * # Panics
+ * Will panic if `testvectors/chunk_proofs.txt` is malformed.
+ */
+pub fn chunk_proofs() -> Vec<ChunkProofVector> {
+    records(CHUNK_PROOFS).map(|fields| {
+        let siblings = fields[3].split(',')
+            .filter(|sibling| !sibling.is_empty())
+            .map(|sibling| if sibling == "-" { None } else { Some(String::from(sibling)) })
+            .collect();
+        ChunkProofVector {
+            chunk_size: number(fields[0]),
+            len: number(fields[1]),
+            proof: ChunkProof { index: number(fields[2]), siblings }
+        }
+    }).collect()
+}
+
+/**
+ * # Panics
+ * Will panic if `testvectors/torrent_roots.txt` is malformed.
+ */
+pub fn pieces_roots() -> Vec<PiecesRootVector> {
+    records(TORRENT_ROOTS).map(|fields| PiecesRootVector {
+        piece_length: number(fields[0]),
+        len: number(fields[1]),
+        pieces_root: String::from(fields[2])
+    }).collect()
+}
+
+/*
+ * Splits a vector file into the fields of each non-comment line.
+ */
+fn records(file: &'static str) -> impl Iterator<Item = Vec<&'static str>> {
+    file.lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| line.split_whitespace().collect())
+}
+
+fn number(field: &str) -> usize {
+    field.parse().unwrap_or_else(|_| panic!("Invalid number {} in test vectors", field))
+}
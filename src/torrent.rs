@@ -0,0 +1,208 @@
+/*!
+ * BitTorrent v2 (BEP 52) piece hashing. Currently supports:
+ * - Computing a file's `pieces root` and `piece layers`
+ * - Verifying a piece against its piece layer hash
+ * - Verifying a piece layer against a `pieces root`
+ *
+ * BEP 52 hashes raw bytes with plain SHA-256, so unlike the rest of this crate these
+ * hashes are `[u8; 32]` arrays and carry no domain tag. A file is split into 16 KiB blocks
+ * which form the leaves of a binary Merkle tree. The leaves are padded with zero hashes up
+ * to the next power of two, and the piece layer is the layer of that tree where each hash
+ * covers exactly `piece_length` bytes.
+ *
+ * # Examples
+ *
+ * ```
+ * use std::io::Cursor;
+ * use newton::torrent::FileHashes;
+ *
+ * let data = vec!(1u8; 100_000);
+ * let hashes = FileHashes::from_reader(Cursor::new(&data), 32 * 1024).unwrap();
+ *
+ * assert!(hashes.verify_piece_layer());
+ * assert!(hashes.verify_piece(1, &data[32 * 1024..64 * 1024]));
+ * ```
+ */
+
+use std::io::Read;
+
+use crypto::sha2::Sha256;
+use crypto::digest::Digest;
+
+use blob::fill_chunk;
+
+/// The size of the leaves of a BEP 52 Merkle tree, in bytes.
+pub const BLOCK_SIZE: usize = 16 * 1024;
+
+/// A SHA-256 digest.
+pub type Sha256Hash = [u8; 32];
+
+/**
+ * The BEP 52 hashes of a single file.
+ *
+ * # Fields
+ * `piece_length`: The number of bytes covered by each piece. A power of two, at least `BLOCK_SIZE`.
+ *
+ * `len`: The length of the file, in bytes.
+ *
+ * `pieces_root`: The root of the file's Merkle tree.
+ *
+ * `piece_layer`: The hashes of the layer of the tree where each hash covers `piece_length` bytes.
+ * BEP 52 only stores this layer for files larger than one piece, so it is empty otherwise.
+ */
+pub struct FileHashes {
+    pub piece_length: usize,
+    pub len: u64,
+    pub pieces_root: Sha256Hash,
+    pub piece_layer: Vec<Sha256Hash>
+}
+
+impl FileHashes {
+
+    /**
+     * Reads a file from `reader` and computes its BEP 52 hashes.
+     *
+     * # Errors
+     * Will return an error if `piece_length` is not a power of two of at least `BLOCK_SIZE`,
+     * if the file is empty (BEP 52 gives empty files no `pieces root`), or if reading fails.
+     */
+    pub fn from_reader<R: Read>(mut reader: R, piece_length: usize) -> Result<Self, String> {
+
+        trace_span!("torrent::from_reader", piece_length);
+
+        if piece_length < BLOCK_SIZE || !piece_length.is_power_of_two() {
+            return Err(String::from("Piece length must be a power of two of at least 16 KiB"));
+        }
+
+        let mut blocks = Vec::new();
+        let mut len = 0;
+        let mut block = vec!(0; BLOCK_SIZE);
+
+        loop {
+            let read = fill_chunk(&mut reader, &mut block)?;
+            if read == 0 { break; }
+
+            blocks.push(sha256(&block[..read]));
+            len += read as u64;
+
+            if read < BLOCK_SIZE { break; }
+        }
+
+        if blocks.is_empty() {
+            return Err(String::from("Empty files have no pieces root"));
+        }
+
+        let blocks_per_piece = piece_length / BLOCK_SIZE;
+        let piece_layer = if blocks.len() > blocks_per_piece {
+            blocks.chunks(blocks_per_piece)
+                .map(|piece| merkle_root(piece.to_vec(), blocks_per_piece, [0; 32]))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let pieces_root = if piece_layer.is_empty() {
+            let width = blocks.len().next_power_of_two();
+            merkle_root(blocks, width, [0; 32])
+        } else {
+            let width = piece_layer.len().next_power_of_two();
+            merkle_root(piece_layer.clone(), width, zero_piece_hash(blocks_per_piece))
+        };
+
+        Ok(FileHashes { piece_length, len, pieces_root, piece_layer })
+    }
+
+    /**
+     * Returns the number of pieces in the file, or 0 if `len` is 0.
+     */
+    pub fn piece_count(&self) -> usize {
+        if self.len == 0 { return 0; }
+        ((self.len - 1) / self.piece_length as u64 + 1) as usize
+    }
+
+    /**
+     * Checks that `piece_layer` hashes up to `pieces_root`. A peer should run this check on
+     * the piece layer it receives before using it to verify pieces. Files no larger than one
+     * piece have no piece layer, in which case this only checks that there is none.
+     */
+    pub fn verify_piece_layer(&self) -> bool {
+        if self.piece_count() == 1 {
+            return self.piece_layer.is_empty();
+        }
+        if self.piece_layer.len() != self.piece_count() {
+            return false;
+        }
+
+        let width = self.piece_layer.len().next_power_of_two();
+        let pad = zero_piece_hash(self.piece_length / BLOCK_SIZE);
+        merkle_root(self.piece_layer.clone(), width, pad) == self.pieces_root
+    }
+
+    /**
+     * Checks that `piece` is the piece at `index` of this file. For files no larger than one
+     * piece, the piece is checked against `pieces_root` directly.
+     */
+    pub fn verify_piece(&self, index: usize, piece: &[u8]) -> bool {
+
+        if index >= self.piece_count() {
+            return false;
+        }
+
+        let start = index as u64 * self.piece_length as u64;
+        let expected_len = ::std::cmp::min(self.piece_length as u64, self.len - start) as usize;
+        if piece.len() != expected_len {
+            return false;
+        }
+
+        let blocks: Vec<Sha256Hash> = piece.chunks(BLOCK_SIZE).map(sha256).collect();
+
+        if self.piece_layer.is_empty() {
+            let width = blocks.len().next_power_of_two();
+            merkle_root(blocks, width, [0; 32]) == self.pieces_root
+        } else {
+            match self.piece_layer.get(index) {
+                Some(hash) => merkle_root(blocks, self.piece_length / BLOCK_SIZE, [0; 32]) == *hash,
+                None => false
+            }
+        }
+    }
+}
+
+/*
+ * Computes the root of a Merkle tree with `width` leaves, where `width` is a power of two,
+ * the first leaves are `layer` and the rest are `pad`.
+ */
+fn merkle_root(mut layer: Vec<Sha256Hash>, width: usize, pad: Sha256Hash) -> Sha256Hash {
+
+    layer.resize(width, pad);
+
+    while layer.len() > 1 {
+        layer = layer.chunks(2).map(|pair| hash_pair(&pair[0], &pair[1])).collect();
+    }
+
+    layer[0]
+}
+
+/*
+ * The hash of a piece made up entirely of zero leaf hashes, used to pad the piece layer.
+ */
+fn zero_piece_hash(blocks_per_piece: usize) -> Sha256Hash {
+    merkle_root(Vec::new(), blocks_per_piece, [0; 32])
+}
+
+fn hash_pair(left: &Sha256Hash, right: &Sha256Hash) -> Sha256Hash {
+    let mut hasher = Sha256::new();
+    hasher.input(left);
+    hasher.input(right);
+    let mut result = [0; 32];
+    hasher.result(&mut result);
+    result
+}
+
+fn sha256(data: &[u8]) -> Sha256Hash {
+    let mut hasher = Sha256::new();
+    hasher.input(data);
+    let mut result = [0; 32];
+    hasher.result(&mut result);
+    result
+}